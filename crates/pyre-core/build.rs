@@ -1,4 +1,6 @@
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
+    // Embedded by `sqlx::migrate!`
+    println!("cargo:rerun-if-changed=../../migrations");
     pyre_build::emit_build_info()
 }
@@ -0,0 +1,91 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicI32, Ordering},
+        RwLock,
+    },
+};
+
+use axum::body::Bytes;
+use uuid::Uuid;
+
+/// Serialized card details of the live card data, cleared when dbsync puts new data live.
+///
+/// Once full, cards are served from the database until the next sync clears it.
+#[derive(Debug, Default)]
+pub struct CardCache {
+    capacity: usize,
+    /// Sync run the live data comes from, 0 before the first sync
+    version: AtomicI32,
+    entries: RwLock<HashMap<Uuid, Bytes>>,
+}
+
+impl CardCache {
+    pub fn new(capacity: usize, version: Option<i32>) -> Self {
+        Self {
+            capacity,
+            version: AtomicI32::new(version.unwrap_or_default()),
+            entries: RwLock::default(),
+        }
+    }
+
+    /// The data version, `None` before the first sync
+    pub fn version(&self) -> Option<i32> {
+        Some(self.version.load(Ordering::Acquire)).filter(|v| *v > 0)
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<Bytes> {
+        self.entries
+            .read()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .get(id)
+            .cloned()
+    }
+
+    /// Caches a card read from the data of `version`, dropped if newer data went live since.
+    pub fn insert(&self, version: Option<i32>, id: Uuid, detail: Bytes) {
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        if self.version() == version && entries.len() < self.capacity {
+            entries.insert(id, detail);
+        }
+    }
+
+    /// Drops every entry, they were read from the data before `version`.
+    pub fn invalidate(&self, version: Option<i32>) {
+        let mut entries = self
+            .entries
+            .write()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        entries.clear();
+        self.version
+            .store(version.unwrap_or_default(), Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_card_cache() {
+        let cache = CardCache::new(1, None);
+        assert_eq!(cache.version(), None);
+
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        cache.insert(None, first, Bytes::from_static(b"{}"));
+        cache.insert(None, second, Bytes::from_static(b"{}"));
+        assert!(cache.get(&first).is_some());
+        assert!(cache.get(&second).is_none(), "over capacity");
+
+        cache.invalidate(Some(7));
+        assert_eq!(cache.version(), Some(7));
+        assert!(cache.get(&first).is_none());
+
+        // Loaded before the sync went live
+        cache.insert(None, first, Bytes::from_static(b"{}"));
+        assert!(cache.get(&first).is_none(), "stale");
+    }
+}
@@ -0,0 +1,59 @@
+use axum::response::IntoResponse;
+
+use crate::error::AppError;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Database(#[from] crate::db::Error),
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Image(#[from] pyre_fs::cas::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid date range: {0}")]
+    InvalidRange(String),
+
+    #[error("invalid query: {0}")]
+    InvalidQuery(String),
+
+    #[error("invalid search: {0}")]
+    InvalidSearch(#[from] super::search::query::ParseError),
+
+    #[error("invalid search: {0}")]
+    InvalidMana(#[from] crate::db::sync::scryfall::mana::ManaError),
+
+    #[error("card not found")]
+    NotFound,
+}
+
+impl From<garde::Report> for Error {
+    fn from(report: garde::Report) -> Self {
+        Error::InvalidQuery(report.to_string())
+    }
+}
+
+impl AppError for Error {
+    fn status_code(&self) -> hyper::StatusCode {
+        match self {
+            Error::InvalidRange(_)
+            | Error::InvalidQuery(_)
+            | Error::InvalidSearch(_)
+            | Error::InvalidMana(_) => hyper::StatusCode::BAD_REQUEST,
+            Error::NotFound | Error::Image(pyre_fs::cas::Error::InvalidHash(_)) => {
+                hyper::StatusCode::NOT_FOUND
+            }
+            Error::Database(_) | Error::Sqlx(_) | Error::Image(_) | Error::Json(_) => {
+                hyper::StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+}
+
+impl IntoResponse for Error {
+    fn into_response(self) -> axum::response::Response {
+        <Self as AppError>::into_response(self)
+    }
+}
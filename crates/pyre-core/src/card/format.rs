@@ -0,0 +1,74 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{NaiveDate, TimeDelta};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+
+use super::{end_of_day, error::Error, start_of_day};
+use crate::{
+    db::sync::scryfall::legality::{self, Legality, LegalityChange},
+    svc::state::AppState,
+};
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct Changes {
+    /// First day of the feed, unbounded when omitted
+    #[garde(skip)]
+    pub from: Option<NaiveDate>,
+    /// Last day of the feed, inclusive, unbounded when omitted
+    #[garde(skip)]
+    pub to: Option<NaiveDate>,
+    /// Only changes to this status, e.g. `banned` for a ban list feed
+    #[garde(skip)]
+    pub status: Option<Legality>,
+    #[serde(default = "default_limit")]
+    #[garde(range(min = 1, max = 200))]
+    pub limit: i64,
+    #[serde(default)]
+    #[garde(range(min = 0, max = 10_000))]
+    pub offset: i64,
+}
+
+fn default_limit() -> i64 {
+    50
+}
+
+/// Status changes of cards in a format, newest first
+pub async fn changes(
+    State(state): State<AppState>,
+    Path(format): Path<String>,
+    Query(query): Query<Changes>,
+) -> Result<impl IntoResponse, Error> {
+    query.validate()?;
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(Error::InvalidRange(format!("{from} is after {to}")));
+        }
+    }
+
+    if !legality::format_exists(state.sql_pool.clone(), &format).await? {
+        return Err(Error::NotFound);
+    }
+
+    let from = query
+        .from
+        .map_or(chrono::DateTime::UNIX_EPOCH, start_of_day);
+    let to = query
+        .to
+        .map_or_else(|| chrono::Utc::now() + TimeDelta::days(1), end_of_day);
+
+    let changes = LegalityChange::for_format(
+        state.sql_pool,
+        &format,
+        query.status,
+        (from, to),
+        (query.limit, query.offset),
+    )
+    .await?;
+
+    Ok(Json(changes))
+}
@@ -0,0 +1,115 @@
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{
+        header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH},
+        HeaderMap,
+        StatusCode,
+    },
+    response::{IntoResponse, Redirect, Response},
+};
+use garde::Validate;
+use pyre_fs::cas::Store;
+use serde::{Deserialize, Serialize};
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+use super::error::Error;
+use crate::{
+    db::sync::{config::ImageSize, scryfall::image::Image},
+    svc::state::AppState,
+};
+
+/// Mirrored images never change, their key is the hash of their content
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+#[derive(Validate, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FaceImage {
+    #[serde(default)]
+    #[garde(range(min = 0, max = 7))]
+    pub face: i16,
+    #[serde(default)]
+    #[garde(skip)]
+    pub size: ImageSize,
+}
+
+/// Redirects to the image of a card face, mirrored when available, on Scryfall otherwise
+pub async fn face(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<FaceImage>,
+) -> Result<impl IntoResponse, Error> {
+    query.validate()?;
+
+    let (uri, image) = Image::for_face(state.sql_pool, id, query.face, query.size)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    Ok(match image {
+        Some(image) => Redirect::temporary(&format!("/images/{}", image.hash)),
+        None => Redirect::temporary(&uri),
+    })
+}
+
+/// A mirrored image by content hash, with a strong `ETag` and cached for a year
+pub async fn get(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    headers: HeaderMap,
+) -> Result<Response, Error> {
+    let store = Store::new(&state.config.server.images);
+    let etag = format!("\"{hash}\"");
+
+    if headers
+        .get(IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| matches(value, &etag))
+        && store.contains(&hash).await?
+    {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(ETAG, etag), (CACHE_CONTROL, IMMUTABLE.to_string())],
+        )
+            .into_response());
+    }
+
+    let image = Image::by_hash(state.sql_pool, &hash)
+        .await?
+        .ok_or(Error::NotFound)?;
+    let file = store.open(&hash).await?.ok_or(Error::NotFound)?;
+
+    Ok((
+        [
+            (CONTENT_TYPE, image.content_type),
+            (CONTENT_LENGTH, image.size.to_string()),
+            (ETAG, etag),
+            (CACHE_CONTROL, IMMUTABLE.to_string()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    )
+        .into_response())
+}
+
+/// Whether an `If-None-Match` header matches the entity tag, using weak comparison
+fn matches(header: &str, etag: &str) -> bool {
+    header
+        .split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_if_none_match() {
+        let etag = "\"abc\"";
+        assert!(matches("\"abc\"", etag));
+        assert!(matches("W/\"abc\"", etag));
+        assert!(matches("\"def\", \"abc\"", etag));
+        assert!(matches("*", etag));
+        assert!(!matches("\"def\"", etag));
+        assert!(!matches("abc", etag));
+    }
+}
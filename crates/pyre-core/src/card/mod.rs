@@ -0,0 +1,153 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    card::error::Error,
+    db::sync::scryfall::{
+        db_card::DbCard,
+        legality,
+        price::{CardPrice, Currency},
+        ruling::Ruling,
+    },
+    svc::state::AppState,
+};
+
+pub mod cache;
+pub mod error;
+pub mod format;
+pub mod image;
+pub mod oracle;
+pub mod search;
+
+/// Longest price series returned by a single request
+const MAX_RANGE_DAYS: i64 = 366;
+
+/// Default range when `from` is omitted
+const DEFAULT_RANGE_DAYS: i64 = 90;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceRange {
+    /// First day of the series, defaults to 90 days before `to`
+    pub from: Option<NaiveDate>,
+    /// Last day of the series, inclusive, defaults to today
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    pub currency: Currency,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegalityDate {
+    /// Day to get the legalities on, defaults to today
+    pub date: Option<NaiveDate>,
+}
+
+/// A card with everything shown on its detail page
+#[derive(Debug, Serialize)]
+pub struct CardDetail {
+    #[serde(flatten)]
+    pub card: DbCard,
+    pub rulings: Vec<Ruling>,
+}
+
+impl CardDetail {
+    pub async fn load(dal: sqlx::PgPool, id: Uuid) -> Result<Option<Self>, Error> {
+        let mut conn = dal.acquire().await?;
+        let Some(card) = DbCard::load(&mut conn, &[id]).await?.remove(&id) else {
+            return Ok(None);
+        };
+        drop(conn);
+
+        let rulings = match card.oracle_id {
+            Some(oracle_id) => Ruling::for_oracle(dal, oracle_id).await?,
+            None => vec![],
+        };
+
+        Ok(Some(Self { card, rulings }))
+    }
+}
+
+/// Card detail with its faces, set and rulings
+pub async fn get(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    let detail = if let Some(detail) = state.cards.get(&id) {
+        detail
+    } else {
+        // Taken first, a sync going live during the load makes the card stale
+        let version = state.cards.version();
+        let detail = CardDetail::load(state.sql_pool, id)
+            .await?
+            .ok_or(Error::NotFound)?;
+        let detail = Bytes::from(serde_json::to_vec(&detail)?);
+        state.cards.insert(version, id, detail.clone());
+        detail
+    };
+
+    Ok(([(header::CONTENT_TYPE, "application/json")], detail))
+}
+
+/// Price series of a card, one point per observed price change
+pub async fn prices(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(range): Query<PriceRange>,
+) -> Result<impl IntoResponse, Error> {
+    let to = range.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = range
+        .from
+        .unwrap_or(to - TimeDelta::days(DEFAULT_RANGE_DAYS));
+
+    if from > to {
+        return Err(Error::InvalidRange(format!("{from} is after {to}")));
+    }
+    if (to - from).num_days() > MAX_RANGE_DAYS {
+        return Err(Error::InvalidRange(format!(
+            "more than {MAX_RANGE_DAYS} days"
+        )));
+    }
+
+    let series = CardPrice::series(
+        state.sql_pool,
+        id,
+        range.currency,
+        start_of_day(from),
+        end_of_day(to),
+    )
+    .await?;
+
+    Ok(Json(series))
+}
+
+/// Legalities of a card, as they were at the end of `date` when given
+pub async fn legalities(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<LegalityDate>,
+) -> Result<impl IntoResponse, Error> {
+    let date = query.date.unwrap_or_else(|| Utc::now().date_naive());
+    let legalities = legality::at(state.sql_pool, id, end_of_day(date)).await?;
+
+    if legalities.is_empty() {
+        return Err(Error::NotFound);
+    }
+
+    Ok(Json(legalities))
+}
+
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_time(chrono::NaiveTime::MIN).and_utc()
+}
+
+/// The last microsecond of a day, the last day of a range is inclusive
+fn end_of_day(date: NaiveDate) -> DateTime<Utc> {
+    start_of_day(date + TimeDelta::days(1)) - TimeDelta::microseconds(1)
+}
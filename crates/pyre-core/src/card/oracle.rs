@@ -0,0 +1,79 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{error::Error, CardDetail};
+use crate::{db::sync::scryfall::oracle::OracleCard, svc::state::AppState};
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct Search {
+    /// Part of the card name, case insensitive
+    #[garde(length(min = 1, max = 100))]
+    pub name: String,
+    #[serde(default = "default_limit")]
+    #[garde(range(min = 1, max = 100))]
+    pub limit: i64,
+    #[serde(default)]
+    #[garde(range(min = 0, max = 10_000))]
+    pub offset: i64,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+/// A unique card with the printing it is shown as
+#[derive(Debug, Serialize)]
+pub struct OracleDetail {
+    #[serde(flatten)]
+    pub oracle: OracleCard,
+    pub canonical: Option<CardDetail>,
+}
+
+/// A unique card with its canonical printing in full
+pub async fn get(
+    State(state): State<AppState>,
+    Path(oracle_id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    let oracle = OracleCard::get(state.sql_pool.clone(), oracle_id)
+        .await?
+        .ok_or(Error::NotFound)?;
+
+    let canonical = match oracle.canonical_card_id {
+        Some(id) => CardDetail::load(state.sql_pool, id).await?,
+        None => None,
+    };
+
+    Ok(Json(OracleDetail { oracle, canonical }))
+}
+
+/// Every printing of a unique card, most recent first
+pub async fn printings(
+    State(state): State<AppState>,
+    Path(oracle_id): Path<Uuid>,
+) -> Result<impl IntoResponse, Error> {
+    let printings = OracleCard::printings(state.sql_pool, oracle_id).await?;
+    if printings.is_empty() {
+        return Err(Error::NotFound);
+    }
+
+    Ok(Json(printings))
+}
+
+/// Cards matching a name, each card once as its canonical printing
+pub async fn search(
+    State(state): State<AppState>,
+    Query(search): Query<Search>,
+) -> Result<impl IntoResponse, Error> {
+    search.validate()?;
+
+    let printings =
+        OracleCard::search(state.sql_pool, &search.name, search.limit, search.offset).await?;
+
+    Ok(Json(printings))
+}
@@ -0,0 +1,77 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use garde::Validate;
+use serde::{Deserialize, Serialize};
+
+use super::error::Error;
+use crate::{
+    db::sync::scryfall::{mana, staging},
+    svc::state::AppState,
+};
+
+pub mod query;
+pub mod sql;
+
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct Search {
+    /// Scryfall-like search, e.g. `t:dragon c>=rg cmc<=4 -is:reprint`,
+    /// see [`query::Query`]
+    #[garde(length(min = 1, max = 1000))]
+    pub q: String,
+    #[serde(default)]
+    #[garde(skip)]
+    pub unique: Unique,
+    #[serde(default = "default_limit")]
+    #[garde(range(min = 1, max = 100))]
+    pub limit: i64,
+    #[serde(default)]
+    #[garde(range(min = 0, max = 10_000))]
+    pub offset: i64,
+}
+
+fn default_limit() -> i64 {
+    20
+}
+
+/// Whether every matching printing is listed or each card once
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Unique {
+    #[default]
+    Cards,
+    Prints,
+}
+
+/// Printings matching a search, ordered by name
+pub async fn search(
+    State(state): State<AppState>,
+    Query(search): Query<Search>,
+) -> Result<impl IntoResponse, Error> {
+    search.validate()?;
+
+    let query: query::Query = search.q.parse()?;
+
+    // Scryfall only publishes some well formed symbols, e.g. `{W/U}` but not `{U/W}`
+    let costs = query.mana_costs();
+    if !costs.is_empty() {
+        let mut conn = state.sql_pool.acquire().await?;
+        let known = mana::known_symbols(&mut conn, staging::LIVE).await?;
+        for cost in costs {
+            cost.validate(&known)?;
+        }
+    }
+
+    let printings = sql::search(
+        state.sql_pool,
+        &query,
+        search.unique,
+        search.limit,
+        search.offset,
+    )
+    .await?;
+
+    Ok(Json(printings))
+}
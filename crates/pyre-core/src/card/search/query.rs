@@ -0,0 +1,763 @@
+use std::str::FromStr;
+
+use crate::db::sync::scryfall::{
+    legality::Legality,
+    mana::{Color, ManaCost, ManaError},
+};
+
+/// Deepest nesting of parentheses and negations
+const MAX_DEPTH: usize = 16;
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+#[error("{kind} at character {pos}")]
+pub struct ParseError {
+    /// 1-based, in characters
+    pub pos: usize,
+    pub kind: ErrorKind,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ErrorKind {
+    #[error("unexpected end of query")]
+    UnexpectedEnd,
+    #[error("unexpected `{0}`")]
+    Unexpected(String),
+    #[error("unclosed parenthesis")]
+    UnclosedParen,
+    #[error("unclosed quote")]
+    UnclosedQuote,
+    #[error("more than {MAX_DEPTH} nested parentheses or negations")]
+    TooDeep,
+    #[error("missing value for `{0}`")]
+    MissingValue(String),
+    #[error("unknown keyword `{0}`")]
+    UnknownKeyword(String),
+    #[error("invalid value `{1}` for `{0}`")]
+    InvalidValue(String, String),
+    #[error("`{0}` does not support `{1}`")]
+    UnsupportedOperator(String, Op),
+}
+
+/// A parsed search, e.g. `t:dragon (c:r or c:g) -is:reprint`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+    Term(Term),
+}
+
+/// A single condition on a card
+#[derive(Debug, Clone, PartialEq)]
+pub enum Term {
+    /// Part of the name, the whole name when `exact`
+    Name {
+        name: String,
+        exact: bool,
+    },
+    /// Words of the type line of any face
+    Type(String),
+    /// A phrase of the rules text of any face
+    Oracle(String),
+    Artist(String),
+    Keyword(String),
+    Set(String),
+    Lang(String),
+    Colors {
+        field: ColorField,
+        cmp: Cmp,
+        colors: Vec<Color>,
+    },
+    /// Two colors or more
+    Multicolor(ColorField),
+    Stat {
+        stat: Stat,
+        cmp: Cmp,
+        value: StatValue,
+    },
+    /// Mana symbols of any face, at least these ones unless `Eq`
+    Mana {
+        cmp: Cmp,
+        cost: ManaCost,
+    },
+    Legality {
+        format: String,
+        status: Legality,
+    },
+    Rarity {
+        cmp: Cmp,
+        rarity: Rarity,
+    },
+    Usd {
+        cmp: Cmp,
+        value: f32,
+    },
+    Is(Flag),
+}
+
+/// Operator between a keyword and its value
+#[derive(strum::Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    #[strum(serialize = ":")]
+    Colon,
+    #[strum(serialize = "=")]
+    Eq,
+    #[strum(serialize = "!=")]
+    Ne,
+    #[strum(serialize = "<")]
+    Lt,
+    #[strum(serialize = "<=")]
+    Le,
+    #[strum(serialize = ">")]
+    Gt,
+    #[strum(serialize = ">=")]
+    Ge,
+}
+
+/// A comparison, on numbers or on sets of colors
+#[derive(strum::Display, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    #[strum(serialize = "=")]
+    Eq,
+    #[strum(serialize = "<>")]
+    Ne,
+    #[strum(serialize = "<")]
+    Lt,
+    #[strum(serialize = "<=")]
+    Le,
+    #[strum(serialize = ">")]
+    Gt,
+    #[strum(serialize = ">=")]
+    Ge,
+}
+
+impl ParseError {
+    fn at(pos: usize, kind: ErrorKind) -> Self {
+        Self { pos: pos + 1, kind }
+    }
+}
+
+impl Op {
+    /// The comparison of the operator, `colon` being what `:` means for the keyword
+    fn cmp(self, colon: Cmp) -> Cmp {
+        match self {
+            Self::Colon => colon,
+            Self::Eq => Cmp::Eq,
+            Self::Ne => Cmp::Ne,
+            Self::Lt => Cmp::Lt,
+            Self::Le => Cmp::Le,
+            Self::Gt => Cmp::Gt,
+            Self::Ge => Cmp::Ge,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorField {
+    Color,
+    Identity,
+}
+
+/// A numeric characteristic of a face
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    ManaValue,
+    Power,
+    Toughness,
+    Loyalty,
+}
+
+impl Stat {
+    pub const fn column(self) -> &'static str {
+        match self {
+            Self::ManaValue => "cmc",
+            Self::Power => "power_value",
+            Self::Toughness => "toughness_value",
+            Self::Loyalty => "loyalty_value",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "cmc" | "mv" | "manavalue" => Some(Self::ManaValue),
+            "pow" | "power" => Some(Self::Power),
+            "tou" | "toughness" => Some(Self::Toughness),
+            "loy" | "loyalty" => Some(Self::Loyalty),
+            _ => None,
+        }
+    }
+}
+
+/// What a stat is compared with, e.g. `pow>tou`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatValue {
+    Number(f32),
+    Stat(Stat),
+}
+
+/// Rarities in the order Scryfall compares them
+#[derive(strum::Display, strum::EnumString, Debug, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Rarity {
+    #[strum(serialize = "common", serialize = "c")]
+    Common = 1,
+    #[strum(serialize = "uncommon", serialize = "u")]
+    Uncommon,
+    #[strum(serialize = "rare", serialize = "r")]
+    Rare,
+    #[strum(serialize = "special", serialize = "s")]
+    Special,
+    #[strum(serialize = "mythic", serialize = "m")]
+    Mythic,
+    #[strum(serialize = "bonus", serialize = "b")]
+    Bonus,
+}
+
+#[derive(strum::EnumString, Debug, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase", ascii_case_insensitive)]
+pub enum Flag {
+    /// Printed before, in an earlier release
+    Reprint,
+    Foil,
+    Nonfoil,
+    Oversized,
+    /// From a set only released online
+    Digital,
+}
+
+impl Query {
+    /// Costs of every `m:` term, which are only checked for syntax when parsed
+    pub fn mana_costs(&self) -> Vec<&ManaCost> {
+        match self {
+            Self::And(all) | Self::Or(all) => all.iter().flat_map(Self::mana_costs).collect(),
+            Self::Not(query) => query.mana_costs(),
+            Self::Term(Term::Mana { cost, .. }) => vec![cost],
+            Self::Term(_) => vec![],
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = ParseError;
+
+    fn from_str(q: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: q.chars().collect(),
+            pos: 0,
+            depth: 0,
+        };
+
+        let query = parser.or()?;
+        parser.skip_ws();
+        match parser.peek() {
+            Some(c) => Err(parser.error(ErrorKind::Unexpected(c.to_string()))),
+            None => Ok(query),
+        }
+    }
+}
+
+/// Recursive descent over `or := and ("or" and)*`, `and := unary+`,
+/// `unary := "-" unary | "(" or ")" | term`.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn error(&self, kind: ErrorKind) -> ParseError {
+        ParseError::at(self.pos, kind)
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&f) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// Consumes `word` when it stands alone, case insensitive.
+    fn keyword(&mut self, word: &str) -> bool {
+        let end = self.pos + word.len();
+        let matches = self
+            .chars
+            .get(self.pos..end)
+            .is_some_and(|c| c.iter().collect::<String>().eq_ignore_ascii_case(word))
+            && self
+                .chars
+                .get(end)
+                .is_some_and(|c| c.is_whitespace() || *c == '(');
+
+        if matches {
+            self.pos = end;
+        }
+        matches
+    }
+
+    fn or(&mut self) -> Result<Query, ParseError> {
+        let mut any = vec![self.and()?];
+        loop {
+            self.skip_ws();
+            if !self.keyword("or") {
+                break;
+            }
+            any.push(self.and()?);
+        }
+
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Query::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<Query, ParseError> {
+        let mut all = vec![];
+        loop {
+            self.skip_ws();
+            let start = self.pos;
+            if self.peek().is_none_or(|c| c == ')') || self.keyword("or") {
+                self.pos = start;
+                break;
+            }
+            self.keyword("and");
+            all.push(self.unary()?);
+        }
+
+        match all.len() {
+            0 => {
+                Err(
+                    self.error(self.peek().map_or(ErrorKind::UnexpectedEnd, |c| {
+                        ErrorKind::Unexpected(c.to_string())
+                    })),
+                )
+            }
+            1 => Ok(all.remove(0)),
+            _ => Ok(Query::And(all)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Query, ParseError> {
+        self.skip_ws();
+        let start = self.pos;
+        match self.peek() {
+            // Negations nest like parentheses, both count toward the depth
+            Some('-') => {
+                self.descend()?;
+                let query = self.unary()?;
+                self.depth -= 1;
+                Ok(Query::Not(Box::new(query)))
+            }
+            Some('(') => {
+                self.descend()?;
+                let query = self.or()?;
+                self.skip_ws();
+                if self.peek() != Some(')') {
+                    return Err(ParseError::at(start, ErrorKind::UnclosedParen));
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(query)
+            }
+            Some(_) => self.term(),
+            None => Err(self.error(ErrorKind::UnexpectedEnd)),
+        }
+    }
+
+    /// Steps over a `-` or `(`, failing past [`MAX_DEPTH`].
+    fn descend(&mut self) -> Result<(), ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(ErrorKind::TooDeep));
+        }
+        self.pos += 1;
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn term(&mut self) -> Result<Query, ParseError> {
+        let start = self.pos;
+        match self.peek() {
+            Some('"') => {
+                let name = self.quoted()?;
+                return Ok(Query::Term(Term::Name { name, exact: false }));
+            }
+            Some('!') => {
+                self.pos += 1;
+                let name = self.value("!")?;
+                return Ok(Query::Term(Term::Name { name, exact: true }));
+            }
+            _ => {}
+        }
+
+        let key = self.take_while(|c| c.is_ascii_alphabetic());
+        if !key.is_empty() {
+            if let Some(op) = self.op() {
+                let value = self.value(&key)?;
+                return keyed(&key.to_ascii_lowercase(), op, value)
+                    .map_err(|kind| ParseError::at(start, kind));
+            }
+        }
+
+        // Any other word is part of the name
+        self.pos = start;
+        let name = self.take_while(|c| !c.is_whitespace() && c != '(' && c != ')');
+        if name.is_empty() {
+            return Err(self.error(ErrorKind::UnexpectedEnd));
+        }
+        Ok(Query::Term(Term::Name { name, exact: false }))
+    }
+
+    fn op(&mut self) -> Option<Op> {
+        let next = self.chars.get(self.pos + 1).copied();
+        let (op, len) = match (self.peek()?, next) {
+            (':', _) => (Op::Colon, 1),
+            ('=', _) => (Op::Eq, 1),
+            ('!', Some('=')) => (Op::Ne, 2),
+            ('<', Some('=')) => (Op::Le, 2),
+            ('<', _) => (Op::Lt, 1),
+            ('>', Some('=')) => (Op::Ge, 2),
+            ('>', _) => (Op::Gt, 1),
+            _ => return None,
+        };
+        self.pos += len;
+        Some(op)
+    }
+
+    fn value(&mut self, key: &str) -> Result<String, ParseError> {
+        let value = if self.peek() == Some('"') {
+            self.quoted()?
+        } else {
+            self.take_while(|c| !c.is_whitespace() && c != ')')
+        };
+
+        if value.trim().is_empty() {
+            return Err(self.error(ErrorKind::MissingValue(key.to_string())));
+        }
+        Ok(value)
+    }
+
+    fn quoted(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        self.pos += 1;
+        let value = self.take_while(|c| c != '"');
+        if self.peek() != Some('"') {
+            return Err(ParseError::at(start, ErrorKind::UnclosedQuote));
+        }
+        self.pos += 1;
+        Ok(value)
+    }
+}
+
+/// The term of `key`, e.g. `cmc<=4`.
+fn keyed(key: &str, op: Op, value: String) -> Result<Query, ErrorKind> {
+    let invalid = |value: &str| ErrorKind::InvalidValue(key.to_string(), value.to_string());
+    let only = |allowed: &[Op]| {
+        if allowed.contains(&op) {
+            Ok(())
+        } else {
+            Err(ErrorKind::UnsupportedOperator(key.to_string(), op))
+        }
+    };
+    let text = [Op::Colon, Op::Eq];
+
+    let term = match key {
+        "name" | "n" => {
+            only(&text)?;
+            Term::Name {
+                name: value,
+                exact: op == Op::Eq,
+            }
+        }
+        "t" | "type" | "o" | "oracle" => {
+            only(&text)?;
+            if !value.chars().any(char::is_alphanumeric) {
+                return Err(invalid(&value));
+            }
+            if key.starts_with('t') {
+                Term::Type(value)
+            } else {
+                Term::Oracle(value)
+            }
+        }
+        "a" | "artist" => {
+            only(&text)?;
+            Term::Artist(value)
+        }
+        "kw" | "keyword" => {
+            only(&text)?;
+            Term::Keyword(value)
+        }
+        "s" | "e" | "set" | "edition" => {
+            only(&text)?;
+            Term::Set(value.to_lowercase())
+        }
+        "lang" => {
+            only(&text)?;
+            Term::Lang(value.to_lowercase())
+        }
+        "c" | "color" | "colors" => colors(ColorField::Color, op, &value).ok_or(invalid(&value))?,
+        "id" | "identity" | "ci" => {
+            colors(ColorField::Identity, op, &value).ok_or(invalid(&value))?
+        }
+        "m" | "mana" => {
+            only(&[Op::Colon, Op::Eq, Op::Ge])?;
+            Term::Mana {
+                cmp: op.cmp(Cmp::Ge),
+                cost: mana(&value).map_err(|_| invalid(&value))?,
+            }
+        }
+        "f" | "format" | "legal" | "banned" | "restricted" => {
+            only(&[Op::Colon])?;
+            Term::Legality {
+                format: value.to_lowercase(),
+                status: match key {
+                    "banned" => Legality::Banned,
+                    "restricted" => Legality::Restricted,
+                    _ => Legality::Legal,
+                },
+            }
+        }
+        "r" | "rarity" => {
+            Term::Rarity {
+                cmp: op.cmp(Cmp::Eq),
+                rarity: value.parse().map_err(|_| invalid(&value))?,
+            }
+        }
+        "usd" => {
+            Term::Usd {
+                cmp: op.cmp(Cmp::Eq),
+                value: number(&value).ok_or(invalid(&value))?,
+            }
+        }
+        "is" | "not" => {
+            only(&[Op::Colon])?;
+            let flag = Term::Is(value.parse().map_err(|_| invalid(&value))?);
+            return Ok(if key == "not" {
+                Query::Not(Box::new(Query::Term(flag)))
+            } else {
+                Query::Term(flag)
+            });
+        }
+        _ => {
+            let stat =
+                Stat::from_key(key).ok_or_else(|| ErrorKind::UnknownKeyword(key.to_string()))?;
+            Term::Stat {
+                stat,
+                cmp: op.cmp(Cmp::Eq),
+                value: stat_value(&value).ok_or(invalid(&value))?,
+            }
+        }
+    };
+
+    Ok(Query::Term(term))
+}
+
+/// A number or another stat, e.g. `tou` in `pow>tou`.
+fn stat_value(value: &str) -> Option<StatValue> {
+    match Stat::from_key(&value.to_ascii_lowercase()) {
+        Some(stat) => Some(StatValue::Stat(stat)),
+        None => number(value).map(StatValue::Number),
+    }
+}
+
+/// `:` is "at least these colors" for colors and "within these colors" for identity,
+/// colorless always means no color at all.
+fn colors(field: ColorField, op: Op, value: &str) -> Option<Term> {
+    let colon = match field {
+        ColorField::Color => Cmp::Ge,
+        ColorField::Identity => Cmp::Le,
+    };
+
+    let colors = match value.to_ascii_lowercase().as_str() {
+        "m" | "multicolor" => return (op == Op::Colon).then_some(Term::Multicolor(field)),
+        "c" | "colorless" => {
+            return Some(Term::Colors {
+                field,
+                cmp: op.cmp(Cmp::Eq),
+                colors: vec![],
+            });
+        }
+        "white" => vec![Color::W],
+        "blue" => vec![Color::U],
+        "black" => vec![Color::B],
+        "red" => vec![Color::R],
+        "green" => vec![Color::G],
+        letters => {
+            let mut colors = letters
+                .chars()
+                .map(|c| c.to_ascii_uppercase().to_string().parse().ok())
+                .collect::<Option<Vec<Color>>>()?;
+            colors.sort();
+            colors.dedup();
+            colors
+        }
+    };
+
+    Some(Term::Colors {
+        field,
+        cmp: op.cmp(colon),
+        colors,
+    })
+}
+
+/// A mana cost, in full or as a shorthand such as `2RR`.
+fn mana(value: &str) -> Result<ManaCost, ManaError> {
+    if value.contains('{') {
+        return value.parse();
+    }
+
+    let mut cost = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        cost.push('{');
+        cost.push(c.to_ascii_uppercase());
+        while c.is_ascii_digit() && chars.peek().is_some_and(char::is_ascii_digit) {
+            cost.extend(chars.next());
+        }
+        cost.push('}');
+    }
+    cost.parse()
+}
+
+fn number(value: &str) -> Option<f32> {
+    value.parse().ok().filter(|n: &f32| n.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(term: Term) -> Query {
+        Query::Term(term)
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query: Query = r#"t:dragon c>=rg cmc<=4 o:"flying" f:commander r:mythic -is:reprint"#
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            Query::And(vec![
+                term(Term::Type("dragon".to_string())),
+                term(Term::Colors {
+                    field: ColorField::Color,
+                    cmp: Cmp::Ge,
+                    colors: vec![Color::R, Color::G],
+                }),
+                term(Term::Stat {
+                    stat: Stat::ManaValue,
+                    cmp: Cmp::Le,
+                    value: StatValue::Number(4.0),
+                }),
+                term(Term::Oracle("flying".to_string())),
+                term(Term::Legality {
+                    format: "commander".to_string(),
+                    status: Legality::Legal,
+                }),
+                term(Term::Rarity {
+                    cmp: Cmp::Eq,
+                    rarity: Rarity::Mythic,
+                }),
+                Query::Not(Box::new(term(Term::Is(Flag::Reprint)))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_groups() {
+        let query: Query = "goblin (id:c or pow>tou) OR !\"Lightning Bolt\""
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            query,
+            Query::Or(vec![
+                Query::And(vec![
+                    term(Term::Name {
+                        name: "goblin".to_string(),
+                        exact: false,
+                    }),
+                    Query::Or(vec![
+                        term(Term::Colors {
+                            field: ColorField::Identity,
+                            cmp: Cmp::Eq,
+                            colors: vec![],
+                        }),
+                        term(Term::Stat {
+                            stat: Stat::Power,
+                            cmp: Cmp::Gt,
+                            value: StatValue::Stat(Stat::Toughness),
+                        }),
+                    ]),
+                ]),
+                term(Term::Name {
+                    name: "Lightning Bolt".to_string(),
+                    exact: true,
+                }),
+            ])
+        );
+
+        let Query::Term(Term::Mana { cmp, cost }) = "m:2RR".parse().unwrap() else {
+            panic!("not a mana term");
+        };
+        assert_eq!((cmp, cost.to_string().as_str()), (Cmp::Ge, "{2}{R}{R}"));
+
+        let query: Query = "m:2RR (t:goblin or -m={U/W})".parse().unwrap();
+        let costs = query
+            .mana_costs()
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(costs, ["{2}{R}{R}", "{U/W}"]);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let kind = |q: &str| q.parse::<Query>().unwrap_err().kind;
+
+        assert_eq!(kind(""), ErrorKind::UnexpectedEnd);
+        assert_eq!(kind("t:elf -"), ErrorKind::UnexpectedEnd);
+        assert_eq!(kind("(t:elf"), ErrorKind::UnclosedParen);
+        assert_eq!(kind("t:elf)"), ErrorKind::Unexpected(")".to_string()));
+        assert_eq!(kind("o:\"flying"), ErrorKind::UnclosedQuote);
+        assert_eq!(kind("t:"), ErrorKind::MissingValue("t".to_string()));
+        assert_eq!(
+            kind("foo:bar"),
+            ErrorKind::UnknownKeyword("foo".to_string())
+        );
+        assert_eq!(
+            kind("c:purple"),
+            ErrorKind::InvalidValue("c".to_string(), "purple".to_string())
+        );
+        assert_eq!(
+            kind("t>elf"),
+            ErrorKind::UnsupportedOperator("t".to_string(), Op::Gt)
+        );
+        assert_eq!(kind(&"(".repeat(MAX_DEPTH + 1)), ErrorKind::TooDeep);
+        assert_eq!(kind(&"-".repeat(MAX_DEPTH + 1)), ErrorKind::TooDeep);
+        assert_eq!(kind(&"-(".repeat(MAX_DEPTH / 2 + 1)), ErrorKind::TooDeep);
+        assert!(format!("{}t:elf", "-".repeat(MAX_DEPTH))
+            .parse::<Query>()
+            .is_ok());
+
+        let err = "t:elf cmc>=x".parse::<Query>().unwrap_err();
+        assert_eq!(err.pos, 7);
+        assert_eq!(
+            err.to_string(),
+            "invalid value `x` for `cmc` at character 7"
+        );
+    }
+}
@@ -0,0 +1,413 @@
+use std::collections::BTreeMap;
+
+use sqlx::{Postgres, QueryBuilder};
+
+use super::{
+    query::{Cmp, ColorField, Flag, Query, Stat, StatValue, Term},
+    Unique,
+};
+use crate::db::{
+    self,
+    sync::scryfall::{
+        legality::Legality,
+        mana::ManaCost,
+        oracle::{escape_like, Printing, PRINTING},
+    },
+};
+
+/// Opens a condition on the faces of the card, closed by the caller
+const ANY_FACE: &str = "EXISTS (SELECT 1 FROM scryfall.card_faces f WHERE f.card_id = c.id AND ";
+
+/// Mana value of the card: the halves of split cards (aftermath included) add up, other cards
+/// have the one of their front
+const MANA_VALUE: &str = r"(
+    SELECT CASE WHEN l.name = 'split' THEN SUM(mf.cmc)
+        ELSE MIN(mf.cmc) FILTER (WHERE mf.face_index = 0) END
+    FROM scryfall.card_faces mf
+    JOIN scryfall.layouts l ON l.id = c.layout_id
+    WHERE mf.card_id = c.id
+    GROUP BY l.name
+)";
+
+/// Rarities by rank, see [`Rarity`](super::query::Rarity)
+const RARITIES: &str = "ARRAY['common', 'uncommon', 'rare', 'special', 'mythic', 'bonus']::text[]";
+
+/// Printings matching `query` by name, one per card unless `unique` is `Prints`.
+pub async fn search(
+    dal: sqlx::PgPool,
+    query: &Query,
+    unique: Unique,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<Printing>, db::Error> {
+    let mut qb = QueryBuilder::new("WITH matches AS (SELECT ");
+    match unique {
+        // The canonical printing when it matches, the most recent one otherwise
+        Unique::Cards => {
+            qb.push(
+                r"
+                DISTINCT ON (COALESCE(c.oracle_id, c.id)) c.id
+                FROM scryfall.cards c
+                LEFT JOIN scryfall.oracle_cards o ON o.oracle_id = c.oracle_id
+                WHERE ",
+            );
+            push(&mut qb, query);
+            qb.push(
+                r"
+                ORDER BY COALESCE(c.oracle_id, c.id),
+                    o.canonical_card_id = c.id DESC NULLS LAST,
+                    c.released_at DESC
+                ",
+            );
+        }
+        Unique::Prints => {
+            qb.push("c.id FROM scryfall.cards c WHERE ");
+            push(&mut qb, query);
+        }
+    }
+
+    qb.push(format!(
+        r"
+        )
+        {PRINTING}
+        WHERE c.id IN (SELECT id FROM matches)
+        ORDER BY c.name, c.released_at DESC, c.id
+        LIMIT "
+    ));
+    qb.push_bind(limit);
+    qb.push(" OFFSET ");
+    qb.push_bind(offset);
+
+    Ok(qb.build_query_as().fetch_all(&dal).await?)
+}
+
+/// Pushes the condition of `query` on the card `c`.
+pub fn push(qb: &mut QueryBuilder<'_, Postgres>, query: &Query) {
+    match query {
+        Query::And(all) => push_all(qb, all, " AND "),
+        Query::Or(any) => push_all(qb, any, " OR "),
+        // A card without a price or a power does not match `usd>1`, so it does `-usd>1`
+        Query::Not(query) => {
+            qb.push("NOT COALESCE(");
+            push(qb, query);
+            qb.push(", false)");
+        }
+        Query::Term(term) => push_term(qb, term),
+    }
+}
+
+fn push_all(qb: &mut QueryBuilder<'_, Postgres>, queries: &[Query], separator: &str) {
+    qb.push("(");
+    for (i, query) in queries.iter().enumerate() {
+        if i > 0 {
+            qb.push(separator);
+        }
+        push(qb, query);
+    }
+    qb.push(")");
+}
+
+fn push_term(qb: &mut QueryBuilder<'_, Postgres>, term: &Term) {
+    match term {
+        Term::Name { name, exact: false } => {
+            qb.push("c.name ILIKE '%' || ");
+            qb.push_bind(escape_like(name));
+            qb.push(" || '%'");
+        }
+        // The name of a face is enough, e.g. `!"Fire"` for Fire // Ice
+        Term::Name { name, exact: true } => {
+            qb.push("(lower(c.name) = lower(");
+            qb.push_bind(name.clone());
+            qb.push(format!(") OR {ANY_FACE}lower(f.name) = lower("));
+            qb.push_bind(name.clone());
+            qb.push(")))");
+        }
+        // Both match the expressions of the GIN indexes on `card_faces`
+        Term::Type(words) => {
+            qb.push(format!(
+                "{ANY_FACE}to_tsvector('english', f.type_line) @@ to_tsquery('english', "
+            ));
+            qb.push_bind(prefix_tsquery(words));
+            qb.push("))");
+        }
+        Term::Oracle(phrase) => {
+            qb.push(format!(
+                "{ANY_FACE}to_tsvector('english', f.oracle_text) @@ phraseto_tsquery('english', "
+            ));
+            qb.push_bind(phrase.clone());
+            qb.push("))");
+        }
+        Term::Artist(artist) => {
+            qb.push("c.artist ILIKE '%' || ");
+            qb.push_bind(escape_like(artist));
+            qb.push(" || '%'");
+        }
+        Term::Keyword(keyword) => {
+            qb.push(
+                r"EXISTS (
+                    SELECT 1 FROM scryfall.card_keywords j
+                    JOIN scryfall.keywords k ON k.id = j.keyword_id
+                    WHERE j.card_id = c.id AND lower(k.name) = lower(",
+            );
+            qb.push_bind(keyword.clone());
+            qb.push("))");
+        }
+        Term::Set(code) => {
+            qb.push("c.set_id IN (SELECT s.id FROM scryfall.sets s WHERE s.code = ");
+            qb.push_bind(code.clone());
+            qb.push(")");
+        }
+        Term::Lang(lang) => {
+            qb.push("c.lang = ");
+            qb.push_bind(lang.clone());
+        }
+        Term::Colors { field, cmp, colors } => {
+            let codes: Vec<String> = colors.iter().map(ToString::to_string).collect();
+            push_colors(qb, *field, *cmp, &codes);
+        }
+        Term::Multicolor(field) => {
+            qb.push(format!("cardinality({}) > 1", color_set(*field)));
+        }
+        Term::Stat { stat, cmp, value } => {
+            qb.push(format!("{ANY_FACE}{} {cmp} ", stat_column(*stat)));
+            match value {
+                StatValue::Number(n) => {
+                    qb.push_bind(*n);
+                }
+                StatValue::Stat(other) => {
+                    qb.push(stat_column(*other));
+                }
+            }
+            qb.push(")");
+        }
+        Term::Mana { cmp, cost } => push_mana(qb, *cmp, cost),
+        Term::Legality { format, status } => push_legality(qb, format, *status),
+        Term::Rarity { cmp, rarity } => {
+            qb.push(format!("array_position({RARITIES}, c.rarity::text) {cmp} "));
+            qb.push_bind(*rarity as i32);
+        }
+        Term::Usd { cmp, value } => {
+            qb.push(format!("c.price_usd {cmp} "));
+            qb.push_bind(*value);
+        }
+        Term::Is(flag) => {
+            qb.push(flag_condition(*flag));
+        }
+    }
+}
+
+fn push_legality(qb: &mut QueryBuilder<'_, Postgres>, format: &str, status: Legality) {
+    qb.push(
+        r"EXISTS (
+            SELECT 1 FROM scryfall.card_legalities l
+            JOIN scryfall.formats fm ON fm.id = l.format_id
+            WHERE l.card_id = c.id AND fm.name = ",
+    );
+    qb.push_bind(format.to_string());
+    // Restricted cards are legal
+    if status == Legality::Legal {
+        qb.push(" AND l.status IN ('legal', 'restricted'))");
+    } else {
+        qb.push(" AND l.status = ");
+        qb.push_bind(status);
+        qb.push(")");
+    }
+}
+
+/// The distinct color codes of the card
+fn color_set(field: ColorField) -> &'static str {
+    match field {
+        ColorField::Color => {
+            r"ARRAY(
+                SELECT DISTINCT co.code::text FROM scryfall.card_faces f
+                JOIN scryfall.card_colors cc ON cc.card_id = f.id
+                JOIN scryfall.colors co ON co.id = cc.color_id
+                WHERE f.card_id = c.id
+            )"
+        }
+        ColorField::Identity => {
+            r"ARRAY(
+                SELECT co.code::text FROM scryfall.card_color_identity ci
+                JOIN scryfall.colors co ON co.id = ci.color_id
+                WHERE ci.card_id = c.id
+            )"
+        }
+    }
+}
+
+/// A stat of the face `f`, the mana value is the one of the whole card.
+fn stat_column(stat: Stat) -> String {
+    if stat == Stat::ManaValue {
+        MANA_VALUE.to_string()
+    } else {
+        format!("f.{}", stat.column())
+    }
+}
+
+/// Compares colors as sets, `>=` being a superset.
+fn push_colors(qb: &mut QueryBuilder<'_, Postgres>, field: ColorField, cmp: Cmp, codes: &[String]) {
+    let set = color_set(field);
+    let (superset, subset) = match cmp {
+        Cmp::Eq | Cmp::Ne => (Some(true), Some(true)),
+        Cmp::Ge => (Some(true), None),
+        Cmp::Le => (None, Some(true)),
+        Cmp::Gt => (Some(true), Some(false)),
+        Cmp::Lt => (Some(false), Some(true)),
+    };
+
+    if cmp == Cmp::Ne {
+        qb.push("NOT ");
+    }
+    qb.push("(true");
+    for (op, is) in [("@>", superset), ("<@", subset)] {
+        if let Some(is) = is {
+            qb.push(if is { " AND " } else { " AND NOT " });
+            qb.push(format!("{set} {op} "));
+            qb.push_bind(codes.to_vec());
+            qb.push("::text[]");
+        }
+    }
+    qb.push(")");
+}
+
+/// Counts each symbol of `cost` in the mana cost of a face, the symbols being
+/// the only `{` of a cost. `Eq` also requires no other symbol.
+fn push_mana(qb: &mut QueryBuilder<'_, Postgres>, cmp: Cmp, cost: &ManaCost) {
+    let mut counts = BTreeMap::<String, i32>::new();
+    for symbol in &cost.symbols {
+        *counts.entry(symbol.to_string()).or_default() += 1;
+    }
+    let op = if cmp == Cmp::Eq { "=" } else { ">=" };
+
+    qb.push(ANY_FACE);
+    qb.push("f.mana_cost IS NOT NULL");
+    for (symbol, count) in counts {
+        qb.push(" AND (length(f.mana_cost) - length(replace(f.mana_cost, ");
+        qb.push_bind(symbol.clone());
+        qb.push(", ''))) / length(");
+        qb.push_bind(symbol);
+        qb.push(format!(") {op} "));
+        qb.push_bind(count);
+    }
+    if cmp == Cmp::Eq {
+        qb.push(" AND length(f.mana_cost) - length(replace(f.mana_cost, '{', '')) = ");
+        qb.push_bind(i32::try_from(cost.symbols.len()).unwrap_or(i32::MAX));
+    }
+    qb.push(")");
+}
+
+fn flag_condition(flag: Flag) -> &'static str {
+    match flag {
+        Flag::Reprint => {
+            r"EXISTS (
+                SELECT 1 FROM scryfall.cards p
+                WHERE p.oracle_id = c.oracle_id AND p.released_at < c.released_at
+            )"
+        }
+        Flag::Foil => "COALESCE(c.foil, false)",
+        Flag::Nonfoil => "COALESCE(c.nonfoil, false)",
+        Flag::Oversized => "COALESCE(c.oversized, false)",
+        Flag::Digital => {
+            "EXISTS (SELECT 1 FROM scryfall.sets s WHERE s.id = c.set_id AND s.digital)"
+        }
+    }
+}
+
+/// Every word as a prefix, e.g. `legend:* & drag:*`, without any tsquery syntax.
+fn prefix_tsquery(words: &str) -> String {
+    words
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("{w}:*"))
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{card::Card, ingest, staging},
+        tests::TestDb,
+    };
+
+    #[tokio::test]
+    async fn test_search() {
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: Card = serde_json::from_slice(&json).unwrap();
+        let id = Uuid::new_v4();
+        card.id = id.to_string();
+        card.oracle_id = None;
+        card.name = format!("Search Elves {id}");
+
+        let json = tokio::fs::read("test/scryfall/split.json")
+            .await
+            .expect("Failed to read file");
+        let mut split: Card = serde_json::from_slice(&json).unwrap();
+        let split_id = Uuid::new_v4();
+        split.id = split_id.to_string();
+        split.oracle_id = None;
+        split.name = format!("Search Fire {split_id}");
+
+        let db = TestDb::new().await;
+        let sql = db.sql.clone();
+        let mut conn = sql.acquire().await.unwrap();
+        ingest::ingest(
+            &mut conn,
+            staging::LIVE,
+            futures::stream::iter([Ok(card), Ok(split)]),
+        )
+        .await
+        .unwrap();
+
+        let found_card = |id: Uuid, q: String| {
+            let sql = sql.clone();
+            async move {
+                let query: Query = q.parse().unwrap();
+                search(sql, &query, Unique::Cards, 10, 0)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .any(|p| p.id == id)
+            }
+        };
+        let found = |q: String| found_card(id, q);
+
+        // Llanowar Elves, {G} 1/1 Creature — Elf Druid, common
+        for q in [
+            "t:elf c:g cmc<=1 o:\"add\" f:commander r<=uncommon -is:reprint",
+            "t:\"elf druid\" id<=gw pow=tou m:G -c:m -(usd>1000 or lang:ja)",
+            "(c=g and m=g) or t:dragon",
+            "a:rahn is:foil -banned:legacy",
+        ] {
+            assert!(found(format!("{id} {q}")).await, "{q}");
+        }
+        for q in [
+            "t:dragon",
+            "c:r",
+            "c>=g -c:g",
+            "pow>tou",
+            "m:GG",
+            "f:standard",
+            "r:mythic",
+        ] {
+            assert!(!found(format!("{id} {q}")).await, "{q}");
+        }
+
+        // Fire // Ice, {1}{R} // {1}{U}, the mana value of a split card adds up its halves
+        for (q, matches) in [("mv=4", true), ("mv<=2", false), ("cmc>3", true)] {
+            assert_eq!(
+                found_card(split_id, format!("{split_id} {q}")).await,
+                matches,
+                "{q}"
+            );
+        }
+
+        drop(conn);
+        db.close().await;
+    }
+}
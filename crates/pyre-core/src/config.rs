@@ -58,6 +58,12 @@ pub struct ServerConfig {
     pub test_cert: bool,
     #[garde(skip)]
     pub secret: PathBuf,
+    /// Root of the card image mirror written by dbsync
+    #[garde(skip)]
+    pub images: PathBuf,
+    /// Card details kept in memory until the next sync
+    #[garde(range(max = 1_000_000))]
+    pub card_cache: usize,
 }
 
 impl Default for ServerConfig {
@@ -68,6 +74,8 @@ impl Default for ServerConfig {
             key: "etc/localhost/local.key".into(),
             test_cert: true,
             secret: "test-master.key".into(),
+            images: "data/images".into(),
+            card_cache: 10_000,
         }
     }
 }
@@ -0,0 +1,312 @@
+use garde::Validate;
+use pyre_fs::DefaultPathProvider;
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    migrate::{Migrate, MigrateError, Migrator},
+    PgConnection,
+};
+use tracing::{info, warn};
+
+use super::Error;
+use crate::{config::HasTelemetry, db::sync::scryfall::lock::LOCK_KEY, svc::state::DbConfig};
+
+/// The `migrations/` directory, embedded at build time
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+/// What the migrate mode does, set from the command line
+#[derive(Debug, Default, Clone, Copy, strum::Display, clap::ValueEnum)]
+#[strum(serialize_all = "lowercase")]
+pub enum Action {
+    /// Apply the pending migrations
+    #[default]
+    Apply,
+    /// Log the state of every migration
+    Status,
+    /// Fail unless every migration is applied with its embedded checksum
+    Check,
+}
+
+/// What `server` and `db-sync` do with the migrations before starting
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, strum::Display, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Startup {
+    /// Leave them to the migrate mode
+    #[default]
+    Off,
+    /// Refuse to start on a database that is not up to date
+    Check,
+    /// Apply the pending migrations
+    Apply,
+}
+
+/// Config of the migrate mode, the database section of any pyre config
+#[derive(Validate, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    #[garde(dive)]
+    pub telemetry: pyre_telemetry::config::Config,
+    #[garde(dive)]
+    pub db: DbConfig,
+}
+
+impl DefaultPathProvider for Config {
+    const DEFAULT_FILENAME: &'static str = "config/pyre-dbsync.toml";
+}
+
+impl HasTelemetry for Config {
+    fn telemetry(&self) -> &pyre_telemetry::config::Config {
+        &self.telemetry
+    }
+}
+
+impl std::fmt::Display for Config {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum State {
+    Applied,
+    Pending,
+    /// Applied, but the file changed since
+    Changed,
+    /// Applied, but no longer embedded
+    Missing,
+    /// Failed halfway, the database needs fixing by hand
+    Dirty,
+}
+
+/// A migration, embedded or applied
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub version: i64,
+    pub description: String,
+    pub state: State,
+}
+
+/// The state of every migration, in version order. Does not write to the database.
+pub async fn status(conn: &mut PgConnection) -> Result<Vec<Status>, Error> {
+    // The table is only made by the first run
+    let tracked: bool = sqlx::query_scalar("SELECT to_regclass('_sqlx_migrations') IS NOT NULL")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    let (applied, dirty) = if tracked {
+        (
+            conn.list_applied_migrations().await?,
+            conn.dirty_version().await?,
+        )
+    } else {
+        (vec![], None)
+    };
+
+    let mut status: Vec<Status> = MIGRATOR
+        .iter()
+        .map(|m| {
+            let state = match applied.iter().find(|a| a.version == m.version) {
+                _ if dirty == Some(m.version) => State::Dirty,
+                Some(a) if a.checksum == m.checksum => State::Applied,
+                Some(_) => State::Changed,
+                None => State::Pending,
+            };
+            Status {
+                version: m.version,
+                description: m.description.to_string(),
+                state,
+            }
+        })
+        .collect();
+
+    status.extend(
+        applied
+            .iter()
+            .filter(|a| !MIGRATOR.version_exists(a.version))
+            .map(|a| {
+                Status {
+                    version: a.version,
+                    description: String::new(),
+                    state: if dirty == Some(a.version) {
+                        State::Dirty
+                    } else {
+                        State::Missing
+                    },
+                }
+            }),
+    );
+    status.sort_by_key(|s| s.version);
+
+    Ok(status)
+}
+
+/// Fails on the first migration that is not applied as embedded.
+pub async fn check(conn: &mut PgConnection) -> Result<(), Error> {
+    let mut pending = vec![];
+    for s in status(conn).await? {
+        match s.state {
+            State::Applied => {}
+            State::Pending => pending.push(s.version),
+            State::Changed => return Err(MigrateError::VersionMismatch(s.version).into()),
+            State::Missing => return Err(MigrateError::VersionMissing(s.version).into()),
+            State::Dirty => return Err(MigrateError::Dirty(s.version).into()),
+        }
+    }
+
+    if !pending.is_empty() {
+        return Err(Error::Pending(pending));
+    }
+
+    Ok(())
+}
+
+/// Applies the pending migrations, after making sure the applied ones did not change.
+///
+/// Holds the dbsync lock meanwhile: a sync staging a copy of the `scryfall` schema would
+/// otherwise swap it live without the migrations.
+pub async fn apply(sql: &sqlx::PgPool) -> Result<(), Error> {
+    let mut lock = sql.acquire().await?;
+    let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+        .bind(LOCK_KEY)
+        .fetch_one(&mut *lock)
+        .await?;
+    if !locked {
+        warn!("a dbsync is running, waiting for it to finish before migrating");
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(LOCK_KEY)
+            .execute(&mut *lock)
+            .await?;
+    }
+
+    let applied = migrate(sql).await;
+
+    sqlx::query("SELECT pg_advisory_unlock($1)")
+        .bind(LOCK_KEY)
+        .execute(&mut *lock)
+        .await?;
+
+    let pending = applied?;
+    info!(applied = pending, "migrations up to date");
+    Ok(())
+}
+
+/// Runs the pending migrations, returns how many there were.
+async fn migrate(sql: &sqlx::PgPool) -> Result<usize, Error> {
+    let pending = status(&mut *sql.acquire().await?)
+        .await?
+        .iter()
+        .filter(|s| s.state == State::Pending)
+        .count();
+
+    MIGRATOR.run(sql).await?;
+    Ok(pending)
+}
+
+/// Checks or applies the migrations as configured, before `server` or `db-sync` start.
+pub async fn startup(db: &DbConfig) -> Result<(), Error> {
+    if db.migrate == Startup::Off {
+        return Ok(());
+    }
+
+    let sql = sqlx::PgPool::connect(&db.pg).await?;
+    match db.migrate {
+        Startup::Off => {}
+        Startup::Check => check(&mut *sql.acquire().await?).await?,
+        Startup::Apply => apply(&sql).await?,
+    }
+    sql.close().await;
+
+    Ok(())
+}
+
+pub async fn start(cfg: Config, action: Action) -> color_eyre::Result<()> {
+    info!(%action, migrations = MIGRATOR.iter().len(), "running migrations");
+
+    let sql = sqlx::PgPool::connect(&cfg.db.pg).await?;
+    match action {
+        Action::Apply => apply(&sql).await?,
+        Action::Check => check(&mut *sql.acquire().await?).await?,
+        Action::Status => {
+            for s in status(&mut *sql.acquire().await?).await? {
+                if s.state == State::Applied {
+                    info!(version = s.version, description = s.description, state = %s.state, "migration");
+                } else {
+                    warn!(version = s.version, description = s.description, state = %s.state, "migration");
+                }
+            }
+        }
+    }
+    sql.close().await;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{migrate::MigrateDatabase, Postgres};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_migrate() {
+        // A database of its own, the shared one is not tracked by sqlx
+        let pg = format!(
+            "{}_migrate_{}",
+            DbConfig::default().pg,
+            uuid::Uuid::new_v4().simple()
+        );
+        Postgres::create_database(&pg).await.unwrap();
+
+        let sql = sqlx::PgPool::connect(&pg).await.unwrap();
+        let mut conn = sql.acquire().await.unwrap();
+        let before = status(&mut conn).await.unwrap();
+        assert_eq!(before.len(), MIGRATOR.iter().len());
+        assert!(before.iter().all(|s| s.state == State::Pending));
+        assert!(
+            matches!(check(&mut conn).await, Err(Error::Pending(v)) if v.len() == before.len())
+        );
+
+        // Waits for a running dbsync
+        sqlx::query("SELECT pg_advisory_lock($1)")
+            .bind(LOCK_KEY)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        let migrating = tokio::spawn({
+            let sql = sql.clone();
+            async move { apply(&sql).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+        assert!(!migrating.is_finished());
+        assert!(matches!(check(&mut conn).await, Err(Error::Pending(_))));
+
+        sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(LOCK_KEY)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        migrating.await.unwrap().unwrap();
+        check(&mut conn).await.unwrap();
+        assert!(status(&mut conn)
+            .await
+            .unwrap()
+            .iter()
+            .all(|s| s.state == State::Applied));
+
+        // Edited after it was applied
+        let last = MIGRATOR.iter().last().unwrap().version;
+        sqlx::query("UPDATE _sqlx_migrations SET checksum = '\\x00' WHERE version = $1")
+            .bind(last)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        assert!(matches!(
+            check(&mut conn).await,
+            Err(Error::Migrate(MigrateError::VersionMismatch(v))) if v == last
+        ));
+
+        drop(conn);
+        sql.close().await;
+        Postgres::drop_database(&pg).await.unwrap();
+    }
+}
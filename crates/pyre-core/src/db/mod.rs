@@ -1,11 +1,16 @@
 #![allow(dead_code)]
 
+pub mod migrate;
 pub mod sync;
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
     #[error(transparent)]
     Sqlx(#[from] sqlx::Error),
+    #[error(transparent)]
+    Migrate(#[from] sqlx::migrate::MigrateError),
+    #[error("pending migrations {0:?}, run the migrate mode")]
+    Pending(Vec<i64>),
 }
 
 #[async_trait::async_trait]
@@ -19,3 +24,37 @@ pub trait Dao: Sized {
     async fn create(&mut self, dal: Self::Dal) -> Result<(), Error>;
     async fn update(&self, dal: Self::Dal) -> Result<Self::Id, Error>;
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use sqlx::{migrate::MigrateDatabase, PgPool, Postgres};
+
+    use crate::svc::state::DbConfig;
+
+    /// A migrated database of its own, tests never write to the shared one
+    pub struct TestDb {
+        pub pg: String,
+        pub sql: PgPool,
+    }
+
+    impl TestDb {
+        pub async fn new() -> Self {
+            let pg = format!(
+                "{}_test_{}",
+                DbConfig::default().pg,
+                uuid::Uuid::new_v4().simple()
+            );
+            Postgres::create_database(&pg).await.unwrap();
+
+            let sql = PgPool::connect(&pg).await.unwrap();
+            super::migrate::MIGRATOR.run(&sql).await.unwrap();
+            Self { pg, sql }
+        }
+
+        /// Drops the database, it is left behind when the test fails before.
+        pub async fn close(self) {
+            self.sql.close().await;
+            Postgres::force_drop_database(&self.pg).await.unwrap();
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use garde::Validate;
 use pyre_fs::DefaultPathProvider;
 use serde::{Deserialize, Serialize};
@@ -36,6 +38,235 @@ pub struct SyncConfig {
     #[garde(dive)]
     pub api: Api,
 
+    /// Seconds between checks for a new bulk file
     #[garde(range(min = 30, max = 360))]
     pub freq: u64,
+
+    #[serde(default)]
+    #[garde(dive)]
+    pub filter: FilterConfig,
+
+    #[serde(default)]
+    #[garde(dive)]
+    pub images: ImageConfig,
+
+    #[serde(default)]
+    #[garde(dive)]
+    pub client: ClientConfig,
+
+    #[serde(default)]
+    #[garde(dive)]
+    pub lock: LockConfig,
+}
+
+/// How dbsync talks to the Scryfall API, see <https://scryfall.com/docs/api#rate-limits-and-good-citizenship>
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct ClientConfig {
+    /// Requests per second, Scryfall asks for no more than 10
+    #[garde(range(min = 1, max = 9))]
+    pub rate: u32,
+
+    /// Requests allowed back to back before the rate applies
+    #[garde(range(min = 1, max = 10))]
+    pub burst: u32,
+
+    /// Retries of a request failing with 429, 5xx or a connection error
+    #[garde(range(max = 10))]
+    pub retries: u32,
+
+    /// Milliseconds before the first retry, doubled on every retry
+    #[garde(range(min = 10, max = 10_000))]
+    pub backoff_ms: u64,
+
+    /// Longest wait between retries in seconds, also caps `Retry-After`
+    #[garde(range(min = 1, max = 600))]
+    pub max_backoff: u64,
+
+    /// Timeout in seconds of metadata calls such as the bulk list or sets
+    #[garde(range(min = 1, max = 120))]
+    pub timeout: u64,
+
+    /// Timeout in seconds of a single bulk download attempt, resumed where it stopped
+    #[garde(range(min = 30, max = 3600))]
+    pub bulk_timeout: u64,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            rate: 8,
+            burst: 4,
+            retries: 5,
+            backoff_ms: 500,
+            max_backoff: 60,
+            timeout: 10,
+            bulk_timeout: 600,
+        }
+    }
+}
+
+/// Only one dbsync runs at a time, the others wait for the lock or exit
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct LockConfig {
+    /// Wait for the running dbsync to stop instead of exiting
+    #[garde(skip)]
+    pub wait: bool,
+
+    /// Seconds between attempts to take the lock while waiting
+    #[garde(range(min = 1, max = 3600))]
+    pub retry: u64,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        Self {
+            wait: true,
+            retry: 30,
+        }
+    }
+}
+
+/// Languages published by Scryfall
+const LANGS: &[&str] = &[
+    "en", "es", "fr", "de", "it", "pt", "ja", "ko", "ru", "zhs", "zht", "he", "la", "grc", "ar",
+    "sa", "ph", "qya",
+];
+
+/// Set types published by Scryfall
+const SET_TYPES: &[&str] = &[
+    "core",
+    "expansion",
+    "masters",
+    "alchemy",
+    "masterpiece",
+    "arsenal",
+    "from_the_vault",
+    "spellbook",
+    "premium_deck",
+    "duel_deck",
+    "draft_innovation",
+    "treasure_chest",
+    "commander",
+    "planechase",
+    "archenemy",
+    "vanguard",
+    "funny",
+    "starter",
+    "box",
+    "promo",
+    "token",
+    "memorabilia",
+    "minigame",
+];
+
+#[derive(strum::Display, Copy, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Game {
+    Paper,
+    Mtgo,
+    Arena,
+}
+
+/// Which cards of the bulk file are ingested
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct FilterConfig {
+    /// Languages to keep, e.g. `["en"]`
+    #[garde(length(min = 1), inner(custom(known(LANGS))))]
+    pub langs: Vec<String>,
+
+    /// Keep cards available in at least one of these games
+    #[garde(length(min = 1))]
+    pub games: Vec<Game>,
+
+    /// Set types to drop, e.g. `["memorabilia", "minigame"]`
+    #[garde(inner(custom(known(SET_TYPES))))]
+    pub exclude_set_types: Vec<String>,
+
+    /// Keep prints only released on a digital platform
+    #[garde(skip)]
+    pub digital: bool,
+
+    /// Keep oversized cards
+    #[garde(skip)]
+    pub oversized: bool,
+}
+
+impl Default for FilterConfig {
+    fn default() -> Self {
+        Self {
+            langs: vec!["en".to_string()],
+            games: vec![Game::Paper, Game::Mtgo, Game::Arena],
+            exclude_set_types: vec![],
+            digital: true,
+            oversized: true,
+        }
+    }
+}
+
+/// Image sizes published by Scryfall for every card face
+#[derive(strum::Display, Copy, Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ImageSize {
+    Small,
+    #[default]
+    Normal,
+    Large,
+    Png,
+    ArtCrop,
+    BorderCrop,
+}
+
+impl ImageSize {
+    /// The `card_faces` column holding the Scryfall URI of this size
+    pub const fn column(self) -> &'static str {
+        match self {
+            Self::Small => "image_small",
+            Self::Normal => "image_normal",
+            Self::Large => "image_large",
+            Self::Png => "image_png",
+            Self::ArtCrop => "image_art_crop",
+            Self::BorderCrop => "image_border_crop",
+        }
+    }
+}
+
+/// Local mirror of card images, downloaded after each sync
+#[derive(Validate, Debug, Clone, Serialize, Deserialize)]
+pub struct ImageConfig {
+    #[garde(skip)]
+    pub enabled: bool,
+
+    /// Root of the content-addressed image store
+    #[garde(skip)]
+    pub path: PathBuf,
+
+    /// Sizes to mirror, e.g. `["normal", "art_crop"]`
+    #[garde(length(min = 1))]
+    pub sizes: Vec<ImageSize>,
+
+    /// Images downloaded at the same time
+    #[garde(range(min = 1, max = 16))]
+    pub concurrency: usize,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: "data/images".into(),
+            sizes: vec![ImageSize::Normal],
+            concurrency: 4,
+        }
+    }
+}
+
+fn known(values: &'static [&'static str]) -> impl Fn(&String, &()) -> garde::Result {
+    move |value, ()| {
+        if values.contains(&value.as_str()) {
+            return Ok(());
+        }
+        Err(garde::Error::new(format!("unknown value {value}")))
+    }
 }
@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use garde::Validate;
 use scryfall::ScryfallSync;
 use serde::{Deserialize, Serialize};
@@ -14,6 +16,25 @@ pub enum Api {
         #[garde(ascii, length(min = 1))]
         path: String,
     },
+    /// A Scryfall bulk file on disk, plain JSON or gzip/zstd compressed.
+    /// Always ingested, symbology, set metadata and rulings are not synced.
+    LocalFile {
+        #[garde(skip)]
+        path: PathBuf,
+    },
+}
+
+/// Runtime options for dbsync, set from the command line
+#[derive(Debug, Default, Clone)]
+pub struct Options {
+    /// Run a single sync and exit instead of scheduling
+    pub once: bool,
+    /// Report what a sync would change without writing, then exit
+    pub dry_run: bool,
+    /// Where to write the dry-run diff as JSON
+    pub diff: Option<PathBuf>,
+    /// Put the data of the sync before the last one back live, then exit
+    pub rollback: bool,
 }
 
 impl Default for Api {
@@ -27,12 +48,16 @@ impl Default for Api {
 
 pub async fn start(
     cfg: config::Config,
+    opts: Options,
     shutdown: tokio::sync::broadcast::Receiver<()>,
 ) -> color_eyre::Result<()> {
-    info!(cfg = %cfg, "creating dbsync");
+    info!(cfg = %cfg, ?opts, "creating dbsync");
+    crate::db::migrate::startup(&cfg.db).await?;
 
-    match cfg.sync.api.clone() {
-        Api::Scryfall { url, path } => ScryfallSync::new(cfg, url, path, shutdown).start().await,
+    match cfg.sync.api {
+        Api::Scryfall { .. } | Api::LocalFile { .. } => {
+            ScryfallSync::new(cfg, opts, shutdown).start().await
+        }
     }
 }
 
@@ -41,14 +66,46 @@ mod tests {
     use pyre_cli::shutdown::Shutdown;
     use pyre_telemetry::Telemetry;
 
-    use crate::db::sync::{config::Config, start};
+    use crate::db::sync::{
+        config::Config,
+        scryfall::staging::{self, tests::SYNC},
+        start,
+        Api,
+        Options,
+    };
 
     #[tokio::test]
     async fn test_scryfall_sync() {
         let _t = Telemetry::default().init_scoped();
+        let _sync = SYNC.lock().await;
         let shutdown = Shutdown::new_with_all_signals().install();
 
-        let cfg = Config::default();
-        start(cfg, shutdown.subscribe()).await.unwrap();
+        let mut cfg = Config::default();
+        cfg.sync.api = Api::LocalFile {
+            path: "test/scryfall/bulk.json.gz".into(),
+        };
+        let opts = Options {
+            once: true,
+            ..Options::default()
+        };
+        let pg = cfg.db.pg.clone();
+        start(cfg, opts, shutdown.subscribe()).await.unwrap();
+
+        // The staged schema went live, the one it replaced is kept for rollback
+        let sql = sqlx::PgPool::connect(&pg).await.unwrap();
+        let schemas: Vec<String> = sqlx::query_scalar(
+            "SELECT nspname::text FROM pg_namespace WHERE nspname = ANY($1) ORDER BY nspname",
+        )
+        .bind([staging::LIVE, staging::NEXT, staging::PREVIOUS])
+        .fetch_all(&sql)
+        .await
+        .unwrap();
+        assert_eq!(schemas, vec![staging::LIVE, staging::PREVIOUS]);
+
+        let cards: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM scryfall.cards")
+            .fetch_one(&sql)
+            .await
+            .unwrap();
+        assert!(cards > 0);
     }
 }
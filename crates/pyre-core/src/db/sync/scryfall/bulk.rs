@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use super::Error;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BulkMetadataList {
     pub data: Vec<BulkMetadata>,
@@ -19,6 +21,15 @@ pub struct BulkMetadata {
     pub download_uri: String,
 }
 
+impl BulkMetadataList {
+    pub fn find(&self, bulk_type: &'static str) -> Result<&BulkMetadata, Error> {
+        self.data
+            .iter()
+            .find(|b| b.bulk_type == bulk_type)
+            .ok_or(Error::MissingBulk(bulk_type))
+    }
+}
+
 impl std::fmt::Display for BulkMetadata {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self:?}")
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::{legality::Legalities, part::Component};
+
 #[allow(clippy::struct_excessive_bools)]
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Card {
@@ -50,6 +52,11 @@ pub struct Card {
 
     pub oversized: bool,
 
+    /// Games this print is available in, e.g. "paper", "mtgo", "arena"
+    pub games: Vec<String>,
+    /// Only released on a digital platform
+    pub digital: bool,
+
     /// Set code
     pub set: String,
     pub set_id: String,
@@ -62,6 +69,9 @@ pub struct Card {
     pub prices: Prices,
 
     pub related_uris: RelatedUris,
+
+    /// Tokens, meld parts and combo pieces, the card itself is usually listed too
+    pub all_parts: Option<Vec<RelatedCard>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -108,37 +118,14 @@ pub struct ImageUris {
     pub border_crop: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Legalities {
-    pub standard: String,
-    pub future: String,
-    pub historic: String,
-    pub timeless: String,
-    pub gladiator: String,
-    pub pioneer: String,
-    pub explorer: String,
-    pub modern: String,
-    pub legacy: String,
-    pub pauper: String,
-    pub vintage: String,
-    pub penny: String,
-    pub commander: String,
-    pub oathbreaker: String,
-    pub standardbrawl: String,
-    pub brawl: String,
-    pub alchemy: String,
-    pub paupercommander: String,
-    pub duel: String,
-    pub oldschool: String,
-    pub premodern: String,
-    pub predh: String,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Prices {
     pub usd: Option<String>,
     pub usd_foil: Option<String>,
     pub usd_etched: Option<String>,
+    pub eur: Option<String>,
+    pub eur_foil: Option<String>,
+    pub tix: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -147,63 +134,56 @@ pub struct RelatedUris {
     pub edhrec: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelatedCard {
+    pub id: String,
+    pub component: Component,
+    pub name: String,
+    pub type_line: String,
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
+
+    use futures::{pin_mut, TryStreamExt};
+    use tokio_util::io::ReaderStream;
     use tracing::info;
 
     use super::*;
+    use crate::db::sync::scryfall::stream::parse_array;
 
     #[tokio::test]
     async fn test_scryfall_card_deserialization() {
         let _t = pyre_telemetry::Telemetry::default().init_scoped();
 
-        let blob_json = tokio::fs::read("default-cards.json")
+        let file = tokio::fs::File::open("default-cards.json")
             .await
             .expect("Failed to read file");
 
-        let data: Vec<Card> = serde_json::from_slice(&blob_json).unwrap();
+        let cards = parse_array::<Card, _, _, _>(ReaderStream::new(file));
+        pin_mut!(cards);
 
-        // Find unique sets
-        let sets: Vec<String> = data
-            .iter()
-            .map(|card| card.set.clone())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
+        let mut sets = HashSet::new();
+        let mut finishes = HashSet::new();
+        let mut layouts = HashSet::new();
+        let mut keywords = HashSet::new();
+        let mut first = None;
 
-        info!(size = ?sets.len(), "unique sets");
-
-        // Find unique finishes
-        let finishes: Vec<String> = data
-            .iter()
-            .flat_map(|card| card.finishes.clone())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
+        while let Some(card) = cards.try_next().await.unwrap() {
+            sets.insert(card.set.clone());
+            finishes.extend(card.finishes.iter().cloned());
+            layouts.insert(card.layout.clone());
+            keywords.extend(card.keywords.iter().cloned());
+            first.get_or_insert(card);
+        }
 
+        info!(size = ?sets.len(), "unique sets");
         info!(size = ?finishes.len(), "unique finishes");
-
-        // Find unique layouts
-        let layouts: Vec<String> = data
-            .iter()
-            .map(|card| card.layout.clone())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
-
         info!(layouts = ?layouts, size = ?layouts.len(), "unique layouts");
-
-        // Find unique keywords
-        let keywords: Vec<String> = data
-            .iter()
-            .flat_map(|card| card.keywords.clone())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect::<Vec<_>>();
-
         info!(size = ?keywords.len(), "unique keywords");
 
-        let serialized = serde_json::to_string_pretty(&data[0]).unwrap();
+        let serialized = serde_json::to_string_pretty(&first.unwrap()).unwrap();
         info!(data = %serialized, "serialized ScryfallCard");
     }
 }
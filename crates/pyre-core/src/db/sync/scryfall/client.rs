@@ -0,0 +1,389 @@
+use std::{num::NonZeroU32, pin::Pin, sync::Arc, time::Duration};
+
+use axum::body::Bytes;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use hyper::{
+    header::{ACCEPT, ACCEPT_ENCODING, ETAG, IF_RANGE, LAST_MODIFIED, RANGE, RETRY_AFTER},
+    HeaderMap,
+    StatusCode,
+};
+use reqwest::header::HeaderValue;
+use serde::de::DeserializeOwned;
+use tracing::{info, warn};
+
+use super::Error;
+use crate::db::sync::config::ClientConfig;
+
+type Body = Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>;
+
+/// A Scryfall API client that stays a good citizen.
+///
+/// - Requests share a GCRA rate limiter, the token bucket equivalent of governor,
+///   so the configured rate holds across concurrent callers.
+/// - 429, 5xx and connection errors are retried with exponential backoff and jitter,
+///   honoring `Retry-After` when Scryfall sends it.
+/// - Bulk downloads are resumed with `Range` requests when the body is cut short.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    limiter: Arc<DefaultDirectRateLimiter>,
+    cfg: ClientConfig,
+}
+
+impl Client {
+    /// # Panics
+    /// If the rate or burst is zero, both are validated with the config.
+    pub fn new(cfg: ClientConfig) -> Result<Self, Error> {
+        let user_agent = format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT, HeaderValue::from_static("application/json"));
+
+        let http = reqwest::Client::builder()
+            .http2_prior_knowledge()
+            .connect_timeout(Duration::from_secs(3))
+            .user_agent(user_agent)
+            .default_headers(headers)
+            .build()?;
+
+        let quota = Quota::per_second(NonZeroU32::new(cfg.rate).expect("rate is validated"))
+            .allow_burst(NonZeroU32::new(cfg.burst).expect("burst is validated"));
+
+        Ok(Self {
+            http,
+            limiter: Arc::new(RateLimiter::direct(quota)),
+            cfg,
+        })
+    }
+
+    /// A metadata call such as the bulk list, sets or symbology.
+    pub async fn json<T>(&self, url: &str) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(self.get(url, HeaderMap::new()).await?.json().await?)
+    }
+
+    /// A successful response to a GET with the metadata timeout, retried when Scryfall allows it.
+    pub async fn get(&self, url: &str, headers: HeaderMap) -> Result<reqwest::Response, Error> {
+        self.send(url, headers, Duration::from_secs(self.cfg.timeout))
+            .await
+    }
+
+    /// Streams the body of a bulk file, resuming where it stopped when the connection drops.
+    /// Each attempt has the bulk timeout, resumes count against the configured retries.
+    pub fn bulk(&self, url: &str) -> impl Stream<Item = Result<Bytes, Error>> + Send + '_ {
+        let download = Download {
+            url: url.to_string(),
+            ..Download::default()
+        };
+
+        futures::stream::try_unfold((download, None::<Body>), move |(mut dl, mut body)| {
+            async move {
+                loop {
+                    let stream = match body.as_mut() {
+                        Some(stream) => stream,
+                        None => body.insert(self.open(&mut dl).await?),
+                    };
+
+                    match stream.next().await {
+                        Some(Ok(mut chunk)) => {
+                            // A server ignoring the range restarts from the first byte
+                            let skip = usize::try_from(dl.skip)
+                                .unwrap_or(usize::MAX)
+                                .min(chunk.len());
+                            dl.skip -= skip as u64;
+                            let chunk = chunk.split_off(skip);
+
+                            if chunk.is_empty() {
+                                continue;
+                            }
+                            dl.received += chunk.len() as u64;
+                            return Ok(Some((chunk, (dl, body))));
+                        }
+                        Some(Err(e)) if dl.resumes < self.cfg.retries => {
+                            let delay = self.backoff(dl.resumes);
+                            warn!(url = dl.url, received = dl.received, ?delay, %e, "bulk download interrupted, resuming");
+                            dl.resumes += 1;
+                            body = None;
+                            tokio::time::sleep(delay).await;
+                        }
+                        Some(Err(e)) => return Err(e.into()),
+                        None => return Ok(None),
+                    }
+                }
+            }
+        })
+    }
+
+    /// Starts or resumes a bulk download after the bytes already received.
+    async fn open(&self, dl: &mut Download) -> Result<Body, Error> {
+        // Ranges are offsets into the body as sent, which must not be decompressed on the way
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        if dl.received > 0 {
+            headers.insert(RANGE, format!("bytes={}-", dl.received).parse().unwrap());
+            if let Some(validator) = &dl.validator {
+                headers.insert(IF_RANGE, validator.clone());
+            }
+        }
+
+        let res = self
+            .send(&dl.url, headers, Duration::from_secs(self.cfg.bulk_timeout))
+            .await?;
+        let validator = res
+            .headers()
+            .get(ETAG)
+            .or_else(|| res.headers().get(LAST_MODIFIED))
+            .cloned();
+
+        if dl.received > 0 {
+            match res.status() {
+                StatusCode::PARTIAL_CONTENT => {
+                    info!(url = dl.url, from = dl.received, "resumed bulk download");
+                }
+                // Without a validator, or with the same one, the file is unchanged
+                _ if dl.validator.is_none() || dl.validator == validator => {
+                    dl.skip = dl.received;
+                }
+                _ => return Err(Error::Bulk("bulk file changed during download")),
+            }
+        }
+
+        dl.validator = validator;
+        Ok(Box::pin(res.bytes_stream()))
+    }
+
+    async fn send(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        timeout: Duration,
+    ) -> Result<reqwest::Response, Error> {
+        let mut attempt = 0;
+
+        loop {
+            self.limiter.until_ready().await;
+
+            let res = self
+                .http
+                .get(url)
+                .headers(headers.clone())
+                .timeout(timeout)
+                .send()
+                .await;
+
+            let (e, retry_after) = match res {
+                Ok(res) if res.status().is_success() => return Ok(res),
+                Ok(res) => {
+                    let status = res.status();
+                    let retry_after = retry_after(res.headers());
+                    let e = Error::Scryfall {
+                        status,
+                        response: res.text().await.unwrap_or_default(),
+                    };
+
+                    if !retryable(status) {
+                        return Err(e);
+                    }
+                    (e, retry_after)
+                }
+                Err(e) if e.is_connect() || e.is_timeout() || e.is_request() => (e.into(), None),
+                Err(e) => return Err(e.into()),
+            };
+
+            if attempt >= self.cfg.retries {
+                return Err(e);
+            }
+
+            let max = Duration::from_secs(self.cfg.max_backoff);
+            let delay = retry_after.map_or_else(|| self.backoff(attempt), |d| d.min(max));
+            warn!(url, attempt, ?delay, %e, "scryfall request failed, retrying");
+
+            attempt += 1;
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Exponential backoff with equal jitter, between half and all of the doubled delay.
+    fn backoff(&self, attempt: u32) -> Duration {
+        let max = self.cfg.max_backoff.saturating_mul(1000);
+        let delay = self
+            .cfg
+            .backoff_ms
+            .saturating_mul(1 << attempt.min(20))
+            .min(max);
+
+        Duration::from_millis(delay / 2 + rand::random_range(0..=delay / 2))
+    }
+}
+
+#[derive(Debug, Default)]
+struct Download {
+    url: String,
+    /// Bytes already returned to the caller
+    received: u64,
+    /// Bytes to drop from a restarted body
+    skip: u64,
+    /// `ETag` or `Last-Modified` of the file being downloaded
+    validator: Option<HeaderValue>,
+    resumes: u32,
+}
+
+fn retryable(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// `Retry-After` in seconds or as an HTTP date.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?;
+
+    if let Ok(secs) = value.parse() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{extract::State, response::IntoResponse, routing::get, Router};
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    const BULK: &[u8] = b"[{\"name\": \"a\"}, {\"name\": \"b\"}, {\"name\": \"c\"}]";
+
+    #[derive(Clone, Default)]
+    struct Calls(Arc<AtomicUsize>);
+
+    impl Calls {
+        fn next(&self) -> usize {
+            self.0.fetch_add(1, Ordering::SeqCst)
+        }
+    }
+
+    /// Rate limited on the first call
+    async fn limited(State(calls): State<Calls>) -> impl IntoResponse {
+        if calls.next() == 0 {
+            return (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(RETRY_AFTER, "0")],
+                "slow down",
+            )
+                .into_response();
+        }
+        axum::Json(vec!["ok"]).into_response()
+    }
+
+    /// Cut short on the first call, then served from the requested range
+    async fn bulk(State(calls): State<Calls>, headers: HeaderMap) -> impl IntoResponse {
+        let etag = [(ETAG, "\"v1\"")];
+        assert_eq!(headers.get(ACCEPT_ENCODING).unwrap(), "identity");
+
+        if calls.next() == 0 {
+            // The first chunk is flushed before the stream is reset
+            let chunks = futures::stream::once(async { Ok(Bytes::from_static(&BULK[..10])) })
+                .chain(futures::stream::once(async {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    Err(std::io::Error::other("connection reset"))
+                }));
+            return (etag, axum::body::Body::from_stream(chunks)).into_response();
+        }
+
+        let from: usize = headers
+            .get(RANGE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                value
+                    .strip_prefix("bytes=")?
+                    .strip_suffix('-')?
+                    .parse()
+                    .ok()
+            })
+            .unwrap_or(0);
+        assert_eq!(headers.get(IF_RANGE).unwrap(), "\"v1\"");
+
+        (StatusCode::PARTIAL_CONTENT, etag, &BULK[from..]).into_response()
+    }
+
+    async fn serve(router: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+        format!("http://{addr}")
+    }
+
+    fn client() -> Client {
+        Client::new(ClientConfig {
+            backoff_ms: 10,
+            ..ClientConfig::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_backoff() {
+        let client = client();
+        for attempt in 0..30 {
+            let delay = client.backoff(attempt);
+            assert!(delay <= Duration::from_secs(client.cfg.max_backoff));
+            assert!(delay >= Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("3"));
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(3)));
+
+        let at = (Utc::now() + chrono::TimeDelta::minutes(1)).to_rfc2822();
+        headers.insert(RETRY_AFTER, at.parse().unwrap());
+        assert!(retry_after(&headers).unwrap() > Duration::from_secs(50));
+    }
+
+    #[tokio::test]
+    async fn test_retry_rate_limited() {
+        let calls = Calls::default();
+        let url = serve(
+            Router::new()
+                .route("/limited", get(limited))
+                .with_state(calls.clone()),
+        )
+        .await;
+
+        let body: Vec<String> = client().json(&format!("{url}/limited")).await.unwrap();
+        assert_eq!(body, vec!["ok"]);
+        assert_eq!(calls.0.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_bulk() {
+        let calls = Calls::default();
+        let url = serve(
+            Router::new()
+                .route("/bulk", get(bulk))
+                .with_state(calls.clone()),
+        )
+        .await;
+
+        let client = client();
+        let body = client
+            .bulk(&format!("{url}/bulk"))
+            .map_ok(|chunk| chunk.to_vec())
+            .try_concat()
+            .await
+            .unwrap();
+
+        assert_eq!(body, BULK);
+        assert_eq!(calls.0.load(Ordering::SeqCst), 2);
+    }
+}
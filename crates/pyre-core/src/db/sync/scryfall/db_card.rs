@@ -1,10 +1,26 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
 use uuid::Uuid;
 
-#[allow(clippy::struct_excessive_bools)]
-#[derive(Debug, Serialize, Deserialize)]
+use super::{
+    card::Card,
+    face,
+    ingest::{CARD_COLUMNS, FACE_COLUMNS},
+    legality::{self, Legalities},
+    mana::ManaCost,
+    part::{self, CardPart},
+    price::{parse_price, CardPrice},
+    stat::StatValue,
+};
+use crate::db;
+
+#[derive(sqlx::FromRow, Debug, Serialize, Deserialize)]
 pub struct DbCard {
     pub id: Uuid,
+    /// Shared by every print of a card, from the first face when the card has none
+    pub oracle_id: Option<Uuid>,
     pub name: String,
     pub lang: String,
 
@@ -18,37 +34,26 @@ pub struct DbCard {
     pub image_status: String,
 
     /// Pulled in from related
+    #[sqlx(skip)]
     pub card_faces: Option<Vec<DbFace>>,
 
     /// Pulled in from many-to-many
+    #[sqlx(skip)]
     pub color_identities: Option<Vec<String>>,
     /// Pulled in from many-to-many
+    #[sqlx(skip)]
     pub keywords: Option<Vec<String>>,
     /// Pulled in from many-to-many
+    #[sqlx(skip)]
     pub finishes: Option<Vec<String>>,
 
-    pub legality_standard: bool,
-    pub legality_future: bool,
-    pub legality_historic: bool,
-    pub legality_timeless: bool,
-    pub legality_gladiator: bool,
-    pub legality_pioneer: bool,
-    pub legality_explorer: bool,
-    pub legality_modern: bool,
-    pub legality_legacy: bool,
-    pub legality_pauper: bool,
-    pub legality_vintage: bool,
-    pub legality_penny: bool,
-    pub legality_commander: bool,
-    pub legality_oathbreaker: bool,
-    pub legality_standardbrawl: bool,
-    pub legality_brawl: bool,
-    pub legality_alchemy: bool,
-    pub legality_paupercommander: bool,
-    pub legality_duel: bool,
-    pub legality_oldschool: bool,
-    pub legality_premodern: bool,
-    pub legality_predh: bool,
+    /// Pulled in from many-to-many, by format name
+    #[sqlx(skip)]
+    pub legalities: Option<Legalities>,
+
+    /// Pulled in from related, in `part_id` order
+    #[sqlx(skip)]
+    pub parts: Option<Vec<CardPart>>,
 
     pub foil: bool,
     pub nonfoil: bool,
@@ -59,26 +64,39 @@ pub struct DbCard {
 
     pub set_id: Uuid,
     /// Pulled in from related
+    #[sqlx(skip)]
     pub set: Option<DbSet>,
 
     pub price_usd: Option<f32>,
     pub price_usd_foil: Option<f32>,
     pub price_usd_etched: Option<f32>,
+    /// Appended to the price history when changed
+    #[sqlx(skip)]
+    pub prices: Option<Vec<CardPrice>>,
 
-    pub edhrec_uri: String,
+    pub edhrec_uri: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(sqlx::FromRow, Debug, Serialize, Deserialize)]
 pub struct DbFace {
-    pub id: Uuid,
+    pub id: i32,
     pub card_id: Uuid,
+    /// Position of the face on the card, starting at 0
+    pub face_index: i16,
     pub name: String,
 
     pub mana_cost: String,
+    /// Parsed `mana_cost`, `None` when it has symbols the parser does not know
+    #[sqlx(skip)]
+    pub mana: Option<ManaCost>,
     pub cmc: f32,
 
     /// Full-text search support
     pub type_line: String,
+    /// Parsed from `type_line`, see [`TypeLine`](super::type_line::TypeLine)
+    pub supertypes: Vec<String>,
+    pub types: Vec<String>,
+    pub subtypes: Vec<String>,
 
     /// Full-text search support
     pub oracle_text: String,
@@ -87,25 +105,262 @@ pub struct DbFace {
     pub flavor_text: Option<String>,
 
     /// Pulled in from many-to-many
+    #[sqlx(skip)]
     pub colors: Option<Vec<String>>,
 
-    pub power: Option<i32>,
-    pub toughness: Option<i32>,
-    pub loyalty: Option<i32>,
+    pub power: Option<StatValue>,
+    pub toughness: Option<StatValue>,
+    pub loyalty: Option<StatValue>,
 
-    pub image_small: String,
-    pub image_normal: String,
-    pub image_large: String,
-    pub image_png: String,
-    pub image_art_crop: String,
-    pub image_border_crop: String,
+    pub image_small: Option<String>,
+    pub image_normal: Option<String>,
+    pub image_large: Option<String>,
+    pub image_png: Option<String>,
+    pub image_art_crop: Option<String>,
+    pub image_border_crop: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
 pub struct DbSet {
     pub id: Uuid,
     pub code: String,
+    pub name: String,
     pub set_type: String,
     pub card_count: i32,
     pub scryfall_uri: String,
+
+    /// Only known from the sets endpoint
+    pub released_at: Option<chrono::NaiveDate>,
+    pub parent_set_code: Option<String>,
+    pub block_code: Option<String>,
+    pub block: Option<String>,
+    pub digital: bool,
+    pub icon_svg_uri: Option<String>,
+}
+
+impl TryFrom<Card> for DbCard {
+    type Error = uuid::Error;
+
+    /// Maps a Scryfall card onto its row representation.
+    /// Related rows (layout, set, normalized faces and junctions) are carried in the
+    /// `Option` fields, `layout_id` is resolved during ingest.
+    fn try_from(card: Card) -> Result<Self, Self::Error> {
+        let id = Uuid::parse_str(&card.id)?;
+        let set_id = Uuid::parse_str(&card.set_id)?;
+
+        let oracle_id = card
+            .oracle_id
+            .as_deref()
+            .or_else(|| {
+                card.faces
+                    .iter()
+                    .flatten()
+                    .find_map(|f| f.oracle_id.as_deref())
+            })
+            .map(Uuid::parse_str)
+            .transpose()?;
+
+        let faces = face::normalize(id, &card);
+        let parts = CardPart::from_related(id, card.all_parts.as_deref().unwrap_or_default())?;
+
+        Ok(Self {
+            id,
+            oracle_id,
+            name: card.name,
+            lang: card.lang,
+            released_at: card.released_at,
+            scryfall_uri: card.scryfall_uri,
+            layout_id: 0,
+            layout: Some(card.layout),
+            image_status: card.image_status,
+            card_faces: Some(faces),
+            color_identities: Some(card.color_identity),
+            keywords: Some(card.keywords),
+            finishes: Some(card.finishes),
+            legalities: Some(card.legalities),
+            parts: Some(parts),
+            foil: card.foil,
+            nonfoil: card.nonfoil,
+            oversized: card.oversized,
+            rarity: card.rarity,
+            artist: card.artist,
+            set_id,
+            set: Some(DbSet {
+                id: set_id,
+                code: card.set,
+                name: card.set_name,
+                set_type: card.set_type,
+                card_count: 0,
+                scryfall_uri: card.scryfall_set_uri,
+                released_at: None,
+                parent_set_code: None,
+                block_code: None,
+                block: None,
+                digital: false,
+                icon_svg_uri: None,
+            }),
+            price_usd: parse_price(card.prices.usd.as_deref()),
+            price_usd_foil: parse_price(card.prices.usd_foil.as_deref()),
+            price_usd_etched: parse_price(card.prices.usd_etched.as_deref()),
+            prices: Some(CardPrice::from_prices(id, &card.prices)),
+            edhrec_uri: card.related_uris.edhrec,
+        })
+    }
+}
+
+impl DbCard {
+    /// Loads cards with their layout, set, faces and related names.
+    /// Price history is not loaded.
+    pub async fn load(
+        conn: &mut PgConnection,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, Self>, db::Error> {
+        let columns = CARD_COLUMNS
+            .iter()
+            .map(|c| format!("c.{c}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let cards: Vec<Self> = sqlx::query_as(&format!(
+            r"
+            SELECT {columns}, l.name AS layout
+            FROM scryfall.cards c
+            JOIN scryfall.layouts l ON l.id = c.layout_id
+            WHERE c.id = ANY($1)
+            "
+        ))
+        .bind(ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut color_identities = related::<Uuid>(
+            conn,
+            r"
+            SELECT j.card_id, c.code
+            FROM scryfall.card_color_identity j
+            JOIN scryfall.colors c ON c.id = j.color_id
+            WHERE j.card_id = ANY($1)
+            ",
+            ids,
+        )
+        .await?;
+
+        let mut keywords = related::<Uuid>(
+            conn,
+            r"
+            SELECT j.card_id, k.name
+            FROM scryfall.card_keywords j
+            JOIN scryfall.keywords k ON k.id = j.keyword_id
+            WHERE j.card_id = ANY($1)
+            ",
+            ids,
+        )
+        .await?;
+
+        let mut finishes = related::<Uuid>(
+            conn,
+            r"
+            SELECT j.card_id, f.name
+            FROM scryfall.card_finishes j
+            JOIN scryfall.finishes f ON f.id = j.finish_id
+            WHERE j.card_id = ANY($1)
+            ",
+            ids,
+        )
+        .await?;
+
+        let mut legalities = legality::for_cards(conn, ids).await?;
+        let mut parts = part::for_cards(conn, ids).await?;
+
+        let set_ids = cards.iter().map(|c| c.set_id).collect::<Vec<_>>();
+        let sets: Vec<DbSet> = sqlx::query_as("SELECT * FROM scryfall.sets WHERE id = ANY($1)")
+            .bind(&set_ids)
+            .fetch_all(&mut *conn)
+            .await?;
+        let sets = sets
+            .into_iter()
+            .map(|s| (s.id, s))
+            .collect::<HashMap<_, _>>();
+
+        let mut cards = cards
+            .into_iter()
+            .map(|mut c| {
+                c.set = sets.get(&c.set_id).cloned();
+                c.color_identities = Some(color_identities.remove(&c.id).unwrap_or_default());
+                c.keywords = Some(keywords.remove(&c.id).unwrap_or_default());
+                c.finishes = Some(finishes.remove(&c.id).unwrap_or_default());
+                c.legalities = Some(legalities.remove(&c.id).unwrap_or_default());
+                c.parts = Some(parts.remove(&c.id).unwrap_or_default());
+                c.card_faces = Some(vec![]);
+                (c.id, c)
+            })
+            .collect::<HashMap<_, _>>();
+
+        for face in DbFace::load(conn, ids).await? {
+            if let Some(faces) = cards
+                .get_mut(&face.card_id)
+                .and_then(|c| c.card_faces.as_mut())
+            {
+                faces.push(face);
+            }
+        }
+
+        Ok(cards)
+    }
+}
+
+impl DbFace {
+    /// Loads the faces of cards with their colors, in face order.
+    pub async fn load(conn: &mut PgConnection, card_ids: &[Uuid]) -> Result<Vec<Self>, db::Error> {
+        let mut faces: Vec<Self> = sqlx::query_as(&format!(
+            "SELECT id, {} FROM scryfall.card_faces WHERE card_id = ANY($1) ORDER BY face_index",
+            FACE_COLUMNS.join(", ")
+        ))
+        .bind(card_ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut colors = related::<i32>(
+            conn,
+            r"
+            SELECT cc.card_id, c.code
+            FROM scryfall.card_colors cc
+            JOIN scryfall.colors c ON c.id = cc.color_id
+            JOIN scryfall.card_faces f ON f.id = cc.card_id
+            WHERE f.card_id = ANY($1)
+            ",
+            card_ids,
+        )
+        .await?;
+
+        for face in &mut faces {
+            face.colors = Some(colors.remove(&face.id).unwrap_or_default());
+            face.mana = face.mana_cost.parse().ok();
+        }
+
+        Ok(faces)
+    }
+}
+
+/// Groups the names of a `(key, name)` query by key.
+async fn related<K>(
+    conn: &mut PgConnection,
+    query: &str,
+    ids: &[Uuid],
+) -> Result<HashMap<K, Vec<String>>, db::Error>
+where
+    K: std::hash::Hash + Eq + for<'r> sqlx::Decode<'r, sqlx::Postgres> + sqlx::Type<sqlx::Postgres>,
+    K: Send + Unpin,
+{
+    let rows: Vec<(K, String)> = sqlx::query_as(query)
+        .bind(ids)
+        .fetch_all(&mut *conn)
+        .await?;
+
+    let mut related = HashMap::<K, Vec<String>>::new();
+    for (key, name) in rows {
+        related.entry(key).or_default().push(name);
+    }
+
+    Ok(related)
 }
@@ -0,0 +1,489 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use futures::{pin_mut, Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::{
+    card::Card,
+    db_card::{DbCard, DbFace, DbSet},
+    ingest::BATCH_SIZE,
+    legality::Legalities,
+    Error,
+};
+
+/// Card keys compared on their own or not written by the ingest
+const CARD_SKIP: &[&str] = &[
+    "id",
+    "layout_id",
+    "card_faces",
+    "set",
+    "prices",
+    "legalities",
+];
+
+/// Set keys only known from the sets endpoint, not compared for sets derived from cards
+const SET_METADATA: &[&str] = &[
+    "card_count",
+    "released_at",
+    "parent_set_code",
+    "block_code",
+    "block",
+    "digital",
+    "icon_svg_uri",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Card,
+    Face,
+    Set,
+    Legality,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    Inserted,
+    Updated,
+    Removed,
+    /// In the database but not in the incoming data, the ingest keeps it as is
+    Missing,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Changes {
+    pub inserted: u64,
+    pub updated: u64,
+    pub removed: u64,
+    pub missing: u64,
+}
+
+impl Changes {
+    fn add(&mut self, change: Change) {
+        match change {
+            Change::Inserted => self.inserted += 1,
+            Change::Updated => self.updated += 1,
+            Change::Removed => self.removed += 1,
+            Change::Missing => self.missing += 1,
+        }
+    }
+}
+
+impl std::fmt::Display for Changes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "+{} ~{} -{} ?{}",
+            self.inserted, self.updated, self.removed, self.missing
+        )
+    }
+}
+
+/// A single difference between the incoming data and the database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub kind: Kind,
+    pub change: Change,
+    /// Card or set id
+    pub id: Uuid,
+    pub name: String,
+    /// Changed keys for updates, the format for legalities
+    pub fields: Vec<String>,
+}
+
+/// What a sync would change, cards and sets missing from the incoming data are kept by the ingest.
+/// Faces and legalities of inserted cards are only counted, not listed.
+/// Legalities count the formats a card becomes legal (inserted) or stops being legal (removed) in.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct DiffReport {
+    pub cards: Changes,
+    pub faces: Changes,
+    pub sets: Changes,
+    pub legalities: Changes,
+    pub entries: Vec<Entry>,
+}
+
+impl DiffReport {
+    fn push(&mut self, kind: Kind, change: Change, id: Uuid, name: &str, fields: Vec<String>) {
+        match kind {
+            Kind::Card => self.cards.add(change),
+            Kind::Face => self.faces.add(change),
+            Kind::Set => self.sets.add(change),
+            Kind::Legality => self.legalities.add(change),
+        }
+
+        self.entries.push(Entry {
+            kind,
+            change,
+            id,
+            name: name.to_string(),
+            fields,
+        });
+    }
+}
+
+impl std::fmt::Display for DiffReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "cards {}, faces {}, sets {}, legalities {}",
+            self.cards, self.faces, self.sets, self.legalities
+        )
+    }
+}
+
+/// Compares incoming cards with the rows of the `scryfall` schema, batch by batch.
+#[derive(Debug, Default)]
+pub struct Diff {
+    batch: Vec<DbCard>,
+    seen: HashSet<Uuid>,
+    /// Sets derived from cards, only compared when no set list is given
+    card_sets: HashMap<Uuid, DbSet>,
+
+    pub report: DiffReport,
+}
+
+impl Diff {
+    /// Compares the complete list of sets, sets missing from it are reported as missing.
+    pub async fn sets(&mut self, conn: &mut PgConnection, sets: Vec<DbSet>) -> Result<(), Error> {
+        let mut existing = load_sets(conn).await?;
+
+        for set in &sets {
+            self.set(existing.remove(&set.id).as_ref(), set, &[])?;
+        }
+
+        for set in existing.values() {
+            self.report
+                .push(Kind::Set, Change::Missing, set.id, &set.name, vec![]);
+        }
+
+        Ok(())
+    }
+
+    /// Queues a card, comparing the batch once it is full.
+    pub async fn push(&mut self, conn: &mut PgConnection, card: Card) -> Result<(), Error> {
+        let mut card = DbCard::try_from(card)?;
+        card.prices = None;
+
+        if let Some(set) = card.set.take() {
+            self.card_sets.entry(set.id).or_insert(set);
+        }
+
+        self.batch.push(card);
+        if self.batch.len() >= BATCH_SIZE {
+            self.flush(conn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Compares all queued cards.
+    pub async fn flush(&mut self, conn: &mut PgConnection) -> Result<(), Error> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        let cards = std::mem::take(&mut self.batch);
+        let ids = cards.iter().map(|c| c.id).collect::<Vec<_>>();
+        let mut existing = DbCard::load(conn, &ids).await?;
+        existing.values_mut().for_each(normalize);
+
+        for card in cards {
+            self.seen.insert(card.id);
+            self.card(existing.remove(&card.id), card)?;
+        }
+
+        Ok(())
+    }
+
+    /// Compares the remaining cards and reports the cards missing from the bulk file.
+    pub async fn finish(
+        mut self,
+        conn: &mut PgConnection,
+        sets: bool,
+    ) -> Result<DiffReport, Error> {
+        self.flush(conn).await?;
+
+        if !sets {
+            let mut existing = load_sets(conn).await?;
+            for set in std::mem::take(&mut self.card_sets).into_values() {
+                self.set(existing.remove(&set.id).as_ref(), &set, SET_METADATA)?;
+            }
+        }
+
+        let cards: Vec<(Uuid, String)> = sqlx::query_as("SELECT id, name FROM scryfall.cards")
+            .fetch_all(&mut *conn)
+            .await?;
+
+        for (id, name) in cards.iter().filter(|(id, _)| !self.seen.contains(id)) {
+            self.report
+                .push(Kind::Card, Change::Missing, *id, name, vec![]);
+        }
+
+        Ok(self.report)
+    }
+
+    fn set(&mut self, old: Option<&DbSet>, new: &DbSet, skip: &[&str]) -> Result<(), Error> {
+        let Some(old) = old else {
+            self.report
+                .push(Kind::Set, Change::Inserted, new.id, &new.name, vec![]);
+            return Ok(());
+        };
+
+        let fields = changed(&object(old)?, &object(new)?, |k| skip.contains(&k));
+        if !fields.is_empty() {
+            self.report
+                .push(Kind::Set, Change::Updated, new.id, &new.name, fields);
+        }
+
+        Ok(())
+    }
+
+    fn card(&mut self, old: Option<DbCard>, mut new: DbCard) -> Result<(), Error> {
+        normalize(&mut new);
+        let new_faces = new.card_faces.take().unwrap_or_default();
+        let new_object = object(&new)?;
+
+        let new_legalities = new.legalities.take().unwrap_or_default();
+
+        let (old_object, old_faces, old_legalities) = match old {
+            Some(mut old) => {
+                let faces = old.card_faces.take().unwrap_or_default();
+                let legalities = old.legalities.take().unwrap_or_default();
+                (Some(object(&old)?), faces, legalities)
+            }
+            None => (None, vec![], Legalities::new()),
+        };
+
+        let Some(old_object) = old_object else {
+            // Only the card is listed, its faces and legalities are counted
+            self.report
+                .push(Kind::Card, Change::Inserted, new.id, &new.name, vec![]);
+            self.report.faces.inserted += new_faces.len() as u64;
+            self.report.legalities.inserted += new_legalities.len() as u64;
+            return Ok(());
+        };
+
+        let fields = changed(&old_object, &new_object, |k| CARD_SKIP.contains(&k));
+        if !fields.is_empty() {
+            self.report
+                .push(Kind::Card, Change::Updated, new.id, &new.name, fields);
+        }
+
+        self.legalities(new.id, &new.name, &old_legalities, &new_legalities);
+        self.faces(new.id, old_faces, &new_faces)
+    }
+
+    /// Formats are listed in name order, the status is not part of the entry.
+    fn legalities(&mut self, card_id: Uuid, name: &str, old: &Legalities, new: &Legalities) {
+        let formats = old.keys().chain(new.keys()).collect::<BTreeSet<_>>();
+
+        for format in formats {
+            let change = match (old.get(format), new.get(format)) {
+                (None, Some(_)) => Change::Inserted,
+                (Some(_), None) => Change::Removed,
+                (Some(old), Some(new)) if old != new => Change::Updated,
+                _ => continue,
+            };
+
+            self.report
+                .push(Kind::Legality, change, card_id, name, vec![format.clone()]);
+        }
+    }
+
+    fn faces(&mut self, card_id: Uuid, old: Vec<DbFace>, new: &[DbFace]) -> Result<(), Error> {
+        let mut old = old
+            .into_iter()
+            .map(|f| (f.face_index, f))
+            .collect::<HashMap<_, _>>();
+
+        for face in new {
+            let Some(old) = old.remove(&face.face_index) else {
+                self.report
+                    .push(Kind::Face, Change::Inserted, card_id, &face.name, vec![]);
+                continue;
+            };
+
+            let fields = changed(&object(&old)?, &object(face)?, |k| k == "id");
+            if !fields.is_empty() {
+                self.report
+                    .push(Kind::Face, Change::Updated, card_id, &face.name, fields);
+            }
+        }
+
+        for face in old.values() {
+            self.report
+                .push(Kind::Face, Change::Removed, card_id, &face.name, vec![]);
+        }
+
+        Ok(())
+    }
+}
+
+/// Compares the cards of the stream with the database without writing anything.
+/// `sets` is the complete set list when known, otherwise sets are derived from the cards.
+pub async fn diff<S>(
+    conn: &mut PgConnection,
+    sets: Option<Vec<DbSet>>,
+    cards: S,
+) -> Result<DiffReport, Error>
+where
+    S: Stream<Item = Result<Card, Error>>,
+{
+    pin_mut!(cards);
+
+    let mut diff = Diff::default();
+
+    let has_sets = sets.is_some();
+    if let Some(sets) = sets {
+        diff.sets(conn, sets).await?;
+    }
+
+    while let Some(card) = cards.try_next().await? {
+        diff.push(conn, card).await?;
+    }
+
+    diff.finish(conn, has_sets).await
+}
+
+/// Sorts the related names so that they compare regardless of database order.
+fn normalize(card: &mut DbCard) {
+    for names in [
+        &mut card.color_identities,
+        &mut card.keywords,
+        &mut card.finishes,
+    ] {
+        names.get_or_insert_default().sort_unstable();
+    }
+
+    for face in card.card_faces.iter_mut().flatten() {
+        face.colors.get_or_insert_default().sort_unstable();
+    }
+}
+
+fn object<T: Serialize>(value: &T) -> Result<Map<String, Value>, Error> {
+    match serde_json::to_value(value)? {
+        Value::Object(object) => Ok(object),
+        _ => Err(Error::Bulk("expected a json object")),
+    }
+}
+
+/// Keys whose value differs, ignoring the skipped ones.
+fn changed<F>(old: &Map<String, Value>, new: &Map<String, Value>, skip: F) -> Vec<String>
+where
+    F: Fn(&str) -> bool,
+{
+    new.iter()
+        .filter(|(k, _)| !skip(k))
+        .filter(|(k, v)| old.get(*k) != Some(*v))
+        .map(|(k, _)| k.clone())
+        .collect()
+}
+
+async fn load_sets(conn: &mut PgConnection) -> Result<HashMap<Uuid, DbSet>, Error> {
+    let sets: Vec<DbSet> = sqlx::query_as("SELECT * FROM scryfall.sets")
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(sets.into_iter().map(|s| (s.id, s)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{ingest, legality::Legality, staging},
+        tests::TestDb,
+    };
+
+    async fn card(id: Uuid) -> Card {
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: Card = serde_json::from_slice(&json).unwrap();
+        card.id = id.to_string();
+        card
+    }
+
+    fn entries(report: &DiffReport, id: Uuid) -> Vec<(Kind, Change, Vec<String>)> {
+        report
+            .entries
+            .iter()
+            .filter(|e| e.id == id)
+            .map(|e| (e.kind, e.change, e.fields.clone()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_diff() {
+        let db = TestDb::new().await;
+        let mut conn = db.sql.acquire().await.unwrap();
+        let id = Uuid::new_v4();
+
+        let cards = futures::stream::iter([Ok(card(id).await)]);
+        let report = diff(&mut conn, None, cards).await.unwrap();
+        assert_eq!(
+            entries(&report, id),
+            vec![(Kind::Card, Change::Inserted, vec![])]
+        );
+
+        let cards = futures::stream::iter([Ok(card(id).await)]);
+        ingest::ingest(&mut conn, staging::LIVE, cards)
+            .await
+            .unwrap();
+
+        // Unchanged once ingested
+        let cards = futures::stream::iter([Ok(card(id).await)]);
+        let report = diff(&mut conn, None, cards).await.unwrap();
+        assert!(entries(&report, id).is_empty());
+
+        let mut changed = card(id).await;
+        changed.rarity = "rare".to_string();
+        changed.keywords.push("Flying".to_string());
+        changed
+            .legalities
+            .insert("modern".to_string(), Legality::Banned);
+        changed.power = Some("2".to_string());
+
+        let report = diff(&mut conn, None, futures::stream::iter([Ok(changed)]))
+            .await
+            .unwrap();
+        assert_eq!(
+            entries(&report, id),
+            vec![
+                (
+                    Kind::Card,
+                    Change::Updated,
+                    vec!["keywords".to_string(), "rarity".to_string()]
+                ),
+                (Kind::Legality, Change::Updated, vec!["modern".to_string()]),
+                (Kind::Face, Change::Updated, vec!["power".to_string()]),
+            ]
+        );
+
+        // Nothing was written by the diff
+        let rarity: String = sqlx::query_scalar("SELECT rarity FROM scryfall.cards WHERE id = $1")
+            .bind(id)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+        assert_eq!(rarity, "common");
+
+        // Not in the bulk file, but the ingest would keep it
+        let report = diff(&mut conn, None, futures::stream::empty())
+            .await
+            .unwrap();
+        assert_eq!(
+            entries(&report, id),
+            vec![(Kind::Card, Change::Missing, vec![])]
+        );
+        assert_eq!((report.cards.removed, report.cards.missing), (0, 1));
+
+        drop(conn);
+        db.close().await;
+    }
+}
@@ -0,0 +1,280 @@
+use uuid::Uuid;
+
+use super::{
+    card::{Card, CardFace, ImageUris},
+    db_card::DbFace,
+    mana::ManaCost,
+    stat::StatValue,
+    type_line::TypeLine,
+};
+
+/// Builds the faces of a card so that every layout ends up with the same shape:
+/// at least one face, each with its own mana cost, mana value, colors and images.
+///
+/// - Cards without `card_faces` (normal, meld, saga, ...) get a single face from the card.
+/// - Faces without images (split, flip, adventure) get the images of the card.
+/// - Faces without colors get them from their mana cost, or from the card (flip, color
+///   indicators are already on the face).
+/// - Faces without a mana value get it from the card when the layout shares it (transform,
+///   flip), otherwise it is computed from the face mana cost (split, adventure, `modal_dfc`).
+pub fn normalize(card_id: Uuid, card: &Card) -> Vec<DbFace> {
+    match card.faces.as_deref() {
+        Some(faces) if !faces.is_empty() => {
+            faces
+                .iter()
+                .zip(0..)
+                .map(|(face, face_index)| from_face(card_id, face_index, face, card))
+                .collect()
+        }
+        _ => vec![from_card(card_id, card)],
+    }
+}
+
+fn from_card(card_id: Uuid, card: &Card) -> DbFace {
+    let mana_cost = card.mana_cost.clone().unwrap_or_default();
+
+    DbFace {
+        id: 0,
+        card_id,
+        face_index: 0,
+        name: card.name.clone(),
+        cmc: to_f32(card.cmc.unwrap_or_else(|| mana_value(&mana_cost))),
+        colors: Some(
+            card.colors
+                .clone()
+                .unwrap_or_else(|| mana_colors(&mana_cost)),
+        ),
+        mana: mana_cost.parse().ok(),
+        mana_cost,
+        type_line: card.type_line.clone().unwrap_or_default(),
+        supertypes: vec![],
+        types: vec![],
+        subtypes: vec![],
+        oracle_text: card.oracle_text.clone().unwrap_or_default(),
+        flavor_text: card.flavor_text.clone(),
+        power: parse_stat(card.power.as_deref()),
+        toughness: parse_stat(card.toughness.as_deref()),
+        loyalty: parse_stat(card.loyalty.as_deref()),
+        image_small: None,
+        image_normal: None,
+        image_large: None,
+        image_png: None,
+        image_art_crop: None,
+        image_border_crop: None,
+    }
+    .with_images(card.image_uris.as_ref())
+    .with_types()
+}
+
+fn from_face(card_id: Uuid, face_index: i16, face: &CardFace, card: &Card) -> DbFace {
+    let cmc = match face.cmc {
+        Some(cmc) => cmc,
+        None if shares_mana_value(&card.layout) => {
+            card.cmc.unwrap_or_else(|| mana_value(&face.mana_cost))
+        }
+        None => mana_value(&face.mana_cost),
+    };
+
+    let colors = face
+        .colors
+        .clone()
+        .or_else(|| (!face.mana_cost.is_empty()).then(|| mana_colors(&face.mana_cost)))
+        .or_else(|| card.colors.clone())
+        .unwrap_or_default();
+
+    DbFace {
+        id: 0,
+        card_id,
+        face_index,
+        name: face.name.clone(),
+        mana_cost: face.mana_cost.clone(),
+        mana: face.mana_cost.parse().ok(),
+        cmc: to_f32(cmc),
+        type_line: face
+            .type_line
+            .clone()
+            .or_else(|| card.type_line.clone())
+            .unwrap_or_default(),
+        supertypes: vec![],
+        types: vec![],
+        subtypes: vec![],
+        oracle_text: face.oracle_text.clone(),
+        flavor_text: face.flavor_text.clone(),
+        colors: Some(colors),
+        power: parse_stat(face.power.as_deref()),
+        toughness: parse_stat(face.toughness.as_deref()),
+        loyalty: parse_stat(face.loyalty.as_deref()),
+        image_small: None,
+        image_normal: None,
+        image_large: None,
+        image_png: None,
+        image_art_crop: None,
+        image_border_crop: None,
+    }
+    .with_images(face.image_uris.as_ref().or(card.image_uris.as_ref()))
+    .with_types()
+}
+
+impl DbFace {
+    fn with_images(mut self, uris: Option<&ImageUris>) -> Self {
+        if let Some(uris) = uris {
+            self.image_small = Some(uris.small.clone());
+            self.image_normal = Some(uris.normal.clone());
+            self.image_large = Some(uris.large.clone());
+            self.image_png = Some(uris.png.clone());
+            self.image_art_crop = Some(uris.art_crop.clone());
+            self.image_border_crop = Some(uris.border_crop.clone());
+        }
+        self
+    }
+
+    fn with_types(mut self) -> Self {
+        let types = TypeLine::parse(&self.type_line);
+        self.supertypes = types.supertypes;
+        self.types = types.types;
+        self.subtypes = types.subtypes;
+        self
+    }
+}
+
+/// The back of a transforming or flip card has the mana value of its front.
+fn shares_mana_value(layout: &str) -> bool {
+    matches!(layout, "transform" | "flip" | "battle")
+}
+
+/// Mana value of a cost such as `{2}{W/U}{G/P}`, zero when it has unknown symbols.
+fn mana_value(cost: &str) -> f64 {
+    cost.parse::<ManaCost>()
+        .map_or(0.0, |cost| cost.mana_value())
+}
+
+/// Colors appearing in the colored symbols of a cost, in WUBRG order.
+fn mana_colors(cost: &str) -> Vec<String> {
+    cost.parse::<ManaCost>()
+        .map(|cost| cost.colors().iter().map(ToString::to_string).collect())
+        .unwrap_or_default()
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn to_f32(value: f64) -> f32 {
+    value as f32
+}
+
+fn parse_stat(stat: Option<&str>) -> Option<StatValue> {
+    stat.map(StatValue::parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::sync::scryfall::db_card::DbCard;
+
+    async fn load(layout: &str) -> Vec<DbFace> {
+        let json = tokio::fs::read(format!("test/scryfall/{layout}.json"))
+            .await
+            .expect("Failed to read file");
+        let card: Card = serde_json::from_slice(&json).unwrap();
+        let card = DbCard::try_from(card).unwrap();
+
+        assert_eq!(card.layout.as_deref(), Some(layout));
+        let faces = card.card_faces.unwrap();
+
+        for (face, index) in faces.iter().zip(0..) {
+            assert_eq!(face.face_index, index);
+            assert!(face.colors.is_some(), "{layout} face without colors");
+            assert!(face.image_normal.is_some(), "{layout} face without images");
+            assert!(
+                !face.type_line.is_empty(),
+                "{layout} face without type line"
+            );
+        }
+
+        faces
+    }
+
+    fn colors(face: &DbFace) -> Vec<&str> {
+        face.colors.iter().flatten().map(String::as_str).collect()
+    }
+
+    #[tokio::test]
+    async fn test_normal() {
+        let faces = load("normal").await;
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].mana_cost, "{G}");
+        assert!((faces[0].cmc - 1.0).abs() < f32::EPSILON);
+        assert_eq!(colors(&faces[0]), vec!["G"]);
+        assert_eq!(faces[0].power, Some(StatValue::parse("1")));
+    }
+
+    #[tokio::test]
+    async fn test_meld() {
+        let faces = load("meld").await;
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].name, "Bruna, the Fading Light");
+        assert!((faces[0].cmc - 7.0).abs() < f32::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_transform() {
+        let faces = load("transform").await;
+        assert_eq!(faces.len(), 2);
+        assert!((faces[0].cmc - 1.0).abs() < f32::EPSILON);
+        // The back face keeps the mana value of the front face
+        assert_eq!(faces[1].mana_cost, "");
+        assert!((faces[1].cmc - 1.0).abs() < f32::EPSILON);
+        assert_eq!(colors(&faces[1]), vec!["U"]);
+        assert_ne!(faces[0].image_normal, faces[1].image_normal);
+    }
+
+    #[tokio::test]
+    async fn test_modal_dfc() {
+        let faces = load("modal_dfc").await;
+        assert_eq!(faces.len(), 2);
+        assert!((faces[0].cmc - 3.0).abs() < f32::EPSILON);
+        // Each face of a modal card has its own mana value
+        assert!(faces[1].cmc.abs() < f32::EPSILON);
+        assert!(colors(&faces[1]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_split() {
+        let faces = load("split").await;
+        assert_eq!(faces.len(), 2);
+        assert!((faces[0].cmc - 2.0).abs() < f32::EPSILON);
+        assert!((faces[1].cmc - 2.0).abs() < f32::EPSILON);
+        assert_eq!(colors(&faces[0]), vec!["R"]);
+        assert_eq!(colors(&faces[1]), vec!["U"]);
+        assert_eq!(faces[0].image_normal, faces[1].image_normal);
+    }
+
+    #[tokio::test]
+    async fn test_flip() {
+        let faces = load("flip").await;
+        assert_eq!(faces.len(), 2);
+        assert!((faces[1].cmc - 1.0).abs() < f32::EPSILON);
+        assert_eq!(colors(&faces[1]), vec!["W"]);
+        assert_eq!(faces[1].power.as_ref().and_then(|p| p.value), Some(3.0));
+    }
+
+    #[tokio::test]
+    async fn test_adventure() {
+        let faces = load("adventure").await;
+        assert_eq!(faces.len(), 2);
+        assert!((faces[0].cmc - 3.0).abs() < f32::EPSILON);
+        assert!((faces[1].cmc - 2.0).abs() < f32::EPSILON);
+        assert_eq!(colors(&faces[1]), vec!["R"]);
+        assert_eq!(faces[1].type_line, "Instant — Adventure");
+        assert_eq!(faces[1].types, vec!["Instant"]);
+        assert_eq!(faces[1].subtypes, vec!["Adventure"]);
+    }
+
+    #[tokio::test]
+    async fn test_reversible_card() {
+        let faces = load("reversible_card").await;
+        assert_eq!(faces.len(), 2);
+        assert!((faces[0].cmc - 5.0).abs() < f32::EPSILON);
+        assert!((faces[1].cmc - 5.0).abs() < f32::EPSILON);
+        assert_eq!(colors(&faces[0]), vec!["U"]);
+        assert_ne!(faces[0].image_normal, faces[1].image_normal);
+    }
+}
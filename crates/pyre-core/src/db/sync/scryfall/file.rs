@@ -0,0 +1,105 @@
+use std::{path::Path, pin::Pin};
+
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use futures::Stream;
+use serde::de::DeserializeOwned;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader};
+use tokio_util::io::ReaderStream;
+
+use super::{stream, Error};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Streams the elements of a bulk file on disk.
+/// Gzip and zstd files are detected from their magic bytes, anything else is read as plain JSON.
+pub async fn open<T>(path: &Path) -> Result<impl Stream<Item = Result<T, Error>>, Error>
+where
+    T: DeserializeOwned,
+{
+    let file = tokio::fs::File::open(path).await?;
+    decode(BufReader::new(file)).await
+}
+
+/// Streams the elements of a possibly compressed JSON array.
+pub async fn decode<T, R>(mut reader: R) -> Result<impl Stream<Item = Result<T, Error>>, Error>
+where
+    T: DeserializeOwned,
+    R: AsyncBufRead + Unpin + Send + 'static,
+{
+    let magic = reader.fill_buf().await?;
+
+    let reader: Pin<Box<dyn AsyncRead + Send>> = if magic.starts_with(GZIP_MAGIC) {
+        let mut decoder = GzipDecoder::new(reader);
+        decoder.multiple_members(true);
+        Box::pin(decoder)
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        Box::pin(ZstdDecoder::new(reader))
+    } else {
+        Box::pin(reader)
+    };
+
+    Ok(stream::parse_array(ReaderStream::new(reader)))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_compression::tokio::bufread::ZstdEncoder;
+    use futures::TryStreamExt;
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+    use crate::db::sync::scryfall::card::Card;
+
+    async fn names<R>(reader: R) -> Vec<String>
+    where
+        R: AsyncBufRead + Unpin + Send + 'static,
+    {
+        decode::<Card, _>(reader)
+            .await
+            .unwrap()
+            .map_ok(|card| card.name)
+            .try_collect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_open_gzip() {
+        let cards = open::<Card>(Path::new("test/scryfall/bulk.json.gz"))
+            .await
+            .unwrap()
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        assert_eq!(cards.len(), 8);
+        assert_eq!(cards[0].name, "Llanowar Elves");
+    }
+
+    #[tokio::test]
+    async fn test_decode_plain_and_zstd() {
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let array = [b"[".as_slice(), &json, b"]"].concat();
+
+        let plain = names(std::io::Cursor::new(array.clone())).await;
+        assert_eq!(plain, vec!["Llanowar Elves"]);
+
+        let mut zstd = vec![];
+        ZstdEncoder::new(array.as_slice())
+            .read_to_end(&mut zstd)
+            .await
+            .unwrap();
+        assert!(zstd.starts_with(ZSTD_MAGIC));
+        assert_eq!(names(std::io::Cursor::new(zstd)).await, plain);
+    }
+
+    #[tokio::test]
+    async fn test_open_missing() {
+        assert!(matches!(
+            open::<Card>(Path::new("test/scryfall/missing.json")).await,
+            Err(Error::Io(_))
+        ));
+    }
+}
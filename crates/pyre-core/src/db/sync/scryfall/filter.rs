@@ -0,0 +1,84 @@
+use super::card::Card;
+use crate::db::sync::config::FilterConfig;
+
+impl FilterConfig {
+    /// Whether a card of the bulk file should be ingested.
+    pub fn matches(&self, card: &Card) -> bool {
+        self.langs.contains(&card.lang)
+            && self
+                .games
+                .iter()
+                .any(|game| card.games.iter().any(|g| *g == game.to_string()))
+            && !self.exclude_set_types.contains(&card.set_type)
+            && (self.digital || !card.digital)
+            && (self.oversized || !card.oversized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use garde::Validate;
+
+    use super::*;
+    use crate::db::sync::config::Game;
+
+    fn normal() -> Card {
+        let json = std::fs::read("test/scryfall/normal.json").expect("Failed to read file");
+        serde_json::from_slice(&json).unwrap()
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = FilterConfig::default();
+        let card = normal();
+        assert!(filter.matches(&card));
+
+        let mut other = normal();
+        other.lang = "ja".to_string();
+        assert!(!filter.matches(&other));
+
+        let mut other = normal();
+        other.games = vec!["mtgo".to_string()];
+        assert!(filter.matches(&other));
+        let paper = FilterConfig {
+            games: vec![Game::Paper],
+            ..FilterConfig::default()
+        };
+        assert!(!paper.matches(&other));
+
+        let mut other = normal();
+        other.digital = true;
+        other.oversized = true;
+        assert!(filter.matches(&other));
+        let physical = FilterConfig {
+            digital: false,
+            oversized: false,
+            ..FilterConfig::default()
+        };
+        assert!(!physical.matches(&other));
+
+        let no_expansions = FilterConfig {
+            exclude_set_types: vec![card.set_type.clone()],
+            ..FilterConfig::default()
+        };
+        assert!(!no_expansions.matches(&card));
+    }
+
+    #[test]
+    fn test_filter_validation() {
+        assert!(FilterConfig::default().validate().is_ok());
+
+        let unknown = FilterConfig {
+            langs: vec!["xx".to_string()],
+            exclude_set_types: vec!["not_a_set_type".to_string()],
+            ..FilterConfig::default()
+        };
+        assert!(unknown.validate().is_err());
+
+        let no_games = FilterConfig {
+            games: vec![],
+            ..FilterConfig::default()
+        };
+        assert!(no_games.validate().is_err());
+    }
+}
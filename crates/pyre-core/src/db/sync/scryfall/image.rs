@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+use futures::{StreamExt, TryStreamExt};
+use hyper::{
+    header::{ACCEPT, CONTENT_TYPE},
+    HeaderMap,
+};
+use pyre_fs::cas::Store;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::{client::Client, Error};
+use crate::db::{
+    self,
+    sync::config::{ImageConfig, ImageSize},
+};
+
+/// A card image mirrored in the local content-addressed store
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Image {
+    /// The Scryfall URI the image was downloaded from
+    pub uri: String,
+    pub hash: String,
+    pub content_type: String,
+    pub size: i64,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl Image {
+    pub async fn by_hash(dal: PgPool, hash: &str) -> Result<Option<Self>, db::Error> {
+        Ok(sqlx::query_as(
+            r"
+            SELECT uri, hash, content_type, size, fetched_at
+            FROM images
+            WHERE hash = $1
+            LIMIT 1
+            ",
+        )
+        .bind(hash)
+        .fetch_optional(&dal)
+        .await?)
+    }
+
+    /// The Scryfall URI of a face image and its mirror, if the image was downloaded.
+    pub async fn for_face(
+        dal: PgPool,
+        card_id: Uuid,
+        face_index: i16,
+        size: ImageSize,
+    ) -> Result<Option<(String, Option<Self>)>, db::Error> {
+        let uri: Option<Option<String>> = sqlx::query_scalar(&format!(
+            "SELECT {} FROM scryfall.card_faces WHERE card_id = $1 AND face_index = $2",
+            size.column()
+        ))
+        .bind(card_id)
+        .bind(face_index)
+        .fetch_optional(&dal)
+        .await?;
+
+        let Some(uri) = uri.flatten() else {
+            return Ok(None);
+        };
+
+        let image = sqlx::query_as(
+            r"
+            SELECT uri, hash, content_type, size, fetched_at
+            FROM images
+            WHERE uri = $1
+            ",
+        )
+        .bind(&uri)
+        .fetch_optional(&dal)
+        .await?;
+
+        Ok(Some((uri, image)))
+    }
+}
+
+/// Downloads the images of the configured sizes that are not mirrored yet.
+/// A failed download is logged and retried on the next sync, it does not fail the run.
+pub async fn mirror(client: &Client, sql: &PgPool, cfg: &ImageConfig) -> Result<u64, Error> {
+    let store = Store::new(&cfg.path);
+    let mut conn = sql.acquire().await?;
+
+    let uris = missing(&mut conn, &cfg.sizes).await?;
+    info!(images = uris.len(), root = %store.root().display(), "mirroring card images");
+
+    let mut downloads = futures::stream::iter(uris)
+        .map(|uri| {
+            async {
+                let res = fetch(client, &uri).await;
+                (uri, res)
+            }
+        })
+        .buffer_unordered(cfg.concurrency);
+
+    let mut count = 0;
+    while let Some((uri, res)) = downloads.next().await {
+        match res {
+            Ok((content_type, bytes)) => {
+                save(&mut conn, &store, &uri, &content_type, &bytes).await?;
+                count += 1;
+            }
+            Err(e) => warn!(%uri, %e, "failed to download card image"),
+        }
+    }
+
+    Ok(count)
+}
+
+/// URIs of the face images of the given sizes without a mirror.
+async fn missing(conn: &mut PgConnection, sizes: &[ImageSize]) -> Result<Vec<String>, Error> {
+    let columns = sizes
+        .iter()
+        .map(|size| format!("SELECT {} AS uri FROM scryfall.card_faces", size.column()))
+        .collect::<Vec<_>>()
+        .join(" UNION ");
+
+    Ok(sqlx::query_scalar(&format!(
+        r"
+        SELECT faces.uri
+        FROM ({columns}) faces
+        WHERE faces.uri IS NOT NULL
+            AND NOT EXISTS (SELECT 1 FROM images i WHERE i.uri = faces.uri)
+        "
+    ))
+    .fetch(conn)
+    .try_collect()
+    .await?)
+}
+
+async fn fetch(client: &Client, uri: &str) -> Result<(String, Vec<u8>), Error> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, "image/*".parse().unwrap());
+    let res = client.get(uri, headers).await?;
+
+    let content_type = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("application/octet-stream")
+        .to_string();
+
+    Ok((content_type, res.bytes().await?.to_vec()))
+}
+
+/// Stores an image and records it, the blob is written before the row that points to it.
+async fn save(
+    conn: &mut PgConnection,
+    store: &Store,
+    uri: &str,
+    content_type: &str,
+    bytes: &[u8],
+) -> Result<Image, Error> {
+    let hash = store.put(bytes).await?;
+
+    Ok(sqlx::query_as(
+        r"
+        INSERT INTO images (uri, hash, content_type, size)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (uri) DO UPDATE
+        SET hash = EXCLUDED.hash,
+            content_type = EXCLUDED.content_type,
+            size = EXCLUDED.size,
+            fetched_at = NOW()
+        RETURNING uri, hash, content_type, size, fetched_at
+        ",
+    )
+    .bind(uri)
+    .bind(hash)
+    .bind(content_type)
+    .bind(i64::try_from(bytes.len()).unwrap_or(i64::MAX))
+    .fetch_one(conn)
+    .await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svc::state::DbConfig;
+
+    #[tokio::test]
+    async fn test_image_mirror() {
+        let sql = PgPool::connect(&DbConfig::default().pg).await.unwrap();
+        let mut conn = sql.acquire().await.unwrap();
+
+        let root = std::env::temp_dir().join(format!("pyre-images-{}", Uuid::new_v4()));
+        let store = Store::new(&root);
+        let uri = format!("https://cards.scryfall.io/test/{}.jpg", Uuid::new_v4());
+
+        let image = save(&mut conn, &store, &uri, "image/jpeg", b"\xff\xd8\xff")
+            .await
+            .unwrap();
+        assert_eq!(image.size, 3);
+        assert!(store.contains(&image.hash).await.unwrap());
+
+        let stored = Image::by_hash(sql.clone(), &image.hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.content_type, "image/jpeg");
+
+        let missing = missing(&mut conn, &[ImageSize::Normal, ImageSize::ArtCrop])
+            .await
+            .unwrap();
+        assert!(!missing.contains(&uri));
+
+        sqlx::query("DELETE FROM images WHERE uri = $1")
+            .bind(&uri)
+            .execute(&sql)
+            .await
+            .unwrap();
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
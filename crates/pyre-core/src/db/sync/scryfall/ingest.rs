@@ -0,0 +1,825 @@
+use std::collections::{HashMap, HashSet};
+
+use futures::{pin_mut, Stream, TryStreamExt};
+use sqlx::{PgConnection, Postgres, QueryBuilder};
+use tracing::warn;
+use uuid::Uuid;
+
+use super::{
+    card::Card,
+    db_card::{DbCard, DbSet},
+    mana::{self, ManaCost},
+    Error,
+};
+
+/// Number of cards upserted per statement, keeps binds well under the postgres limit
+pub const BATCH_SIZE: usize = 500;
+
+pub const CARD_COLUMNS: &[&str] = &[
+    "id",
+    "oracle_id",
+    "name",
+    "lang",
+    "released_at",
+    "scryfall_uri",
+    "layout_id",
+    "image_status",
+    "foil",
+    "nonfoil",
+    "oversized",
+    "rarity",
+    "artist",
+    "set_id",
+    "price_usd",
+    "price_usd_foil",
+    "price_usd_etched",
+    "edhrec_uri",
+];
+
+pub const FACE_COLUMNS: &[&str] = &[
+    "card_id",
+    "face_index",
+    "name",
+    "mana_cost",
+    "cmc",
+    "type_line",
+    "supertypes",
+    "types",
+    "subtypes",
+    "oracle_text",
+    "flavor_text",
+    "power",
+    "toughness",
+    "loyalty",
+    "power_value",
+    "toughness_value",
+    "loyalty_value",
+    "image_small",
+    "image_normal",
+    "image_large",
+    "image_png",
+    "image_art_crop",
+    "image_border_crop",
+];
+
+const SET_COLUMNS: &[&str] = &["id", "code", "name", "set_type", "scryfall_uri"];
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IngestStats {
+    pub cards: u64,
+    pub faces: u64,
+    pub sets: u64,
+    pub rulings: u64,
+    /// Faces whose mana cost does not parse or has a symbol missing from the symbology
+    pub unknown_symbols: u64,
+}
+
+impl std::fmt::Display for IngestStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+/// Buffers cards and upserts them with their related rows in batches.
+/// Statements are schema relative, the caller sets the `search_path`.
+#[derive(Debug, Default)]
+pub struct Ingest {
+    batch: Vec<DbCard>,
+
+    layouts: HashMap<String, i32>,
+    keywords: HashMap<String, i32>,
+    finishes: HashMap<String, i32>,
+    formats: HashMap<String, i32>,
+    /// Symbology of the target schema, mana costs are not checked against it when empty
+    symbols: HashSet<String>,
+
+    pub stats: IngestStats,
+}
+
+impl Ingest {
+    /// Queues a card, flushing the batch once it is full.
+    pub async fn push(&mut self, conn: &mut PgConnection, card: Card) -> Result<(), Error> {
+        let card = DbCard::try_from(card)?;
+        self.check_symbols(&card);
+        self.batch.push(card);
+
+        if self.batch.len() >= BATCH_SIZE {
+            self.flush(conn).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Counts the faces of `card` with a mana cost that can't be searched, they are kept as is.
+    fn check_symbols(&mut self, card: &DbCard) {
+        for face in card.card_faces.iter().flatten() {
+            let checked = match &face.mana {
+                Some(_) if self.symbols.is_empty() => Ok(()),
+                Some(mana) => mana.validate(&self.symbols),
+                None => face.mana_cost.parse::<ManaCost>().map(drop),
+            };
+
+            if let Err(e) = checked {
+                warn!(card = %card.id, face = %face.name, %e, "unexpected mana cost");
+                self.stats.unknown_symbols += 1;
+            }
+        }
+    }
+
+    /// Upserts all queued cards.
+    pub async fn flush(&mut self, conn: &mut PgConnection) -> Result<(), Error> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        let mut cards = std::mem::take(&mut self.batch);
+
+        self.upsert_sets(conn, &cards).await?;
+        self.resolve_layouts(conn, &mut cards).await?;
+        Self::upsert_oracle_cards(conn, &cards).await?;
+        self.upsert_cards(conn, &cards).await?;
+        Self::append_prices(conn, &cards).await?;
+        self.replace_keywords(conn, &cards).await?;
+        self.replace_finishes(conn, &cards).await?;
+        self.replace_legalities(conn, &cards).await?;
+        Self::replace_parts(conn, &cards).await?;
+        Self::replace_color_identities(conn, &cards).await?;
+        self.upsert_faces(conn, &cards).await?;
+
+        Ok(())
+    }
+
+    async fn upsert_sets(
+        &mut self,
+        conn: &mut PgConnection,
+        cards: &[DbCard],
+    ) -> Result<(), Error> {
+        let sets = cards
+            .iter()
+            .filter_map(|c| c.set.as_ref())
+            .map(|s| (s.id, s))
+            .collect::<HashMap<Uuid, &DbSet>>();
+
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "INSERT INTO sets ({}) ",
+            SET_COLUMNS.join(", ")
+        ));
+        qb.push_values(sets.values(), |mut b, s| {
+            b.push_bind(s.id)
+                .push_bind(&s.code)
+                .push_bind(&s.name)
+                .push_bind(&s.set_type)
+                .push_bind(&s.scryfall_uri);
+        });
+        qb.push(on_conflict("id", SET_COLUMNS));
+        qb.build().execute(&mut *conn).await?;
+
+        self.stats.sets += sets.len() as u64;
+        Ok(())
+    }
+
+    async fn resolve_layouts(
+        &mut self,
+        conn: &mut PgConnection,
+        cards: &mut [DbCard],
+    ) -> Result<(), Error> {
+        let names = cards.iter().filter_map(|c| c.layout.as_deref());
+        lookup(conn, "layouts", names, &mut self.layouts).await?;
+
+        for card in cards.iter_mut() {
+            if let Some(id) = card.layout.as_ref().and_then(|l| self.layouts.get(l)) {
+                card.layout_id = *id;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates the oracle card of every printing, the name is the same for all of them.
+    async fn upsert_oracle_cards(conn: &mut PgConnection, cards: &[DbCard]) -> Result<(), Error> {
+        let (ids, names) = cards
+            .iter()
+            .filter_map(|c| c.oracle_id.map(|id| (id, c.name.as_str())))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        sqlx::query(
+            r"
+            INSERT INTO oracle_cards (oracle_id, name)
+            SELECT DISTINCT ON (oracle_id) * FROM UNNEST($1::uuid[], $2::text[]) AS u(oracle_id, name)
+            ON CONFLICT (oracle_id) DO UPDATE SET name = EXCLUDED.name
+            ",
+        )
+        .bind(&ids)
+        .bind(&names)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Picks the printing shown for each oracle card: english, high resolution scan,
+    /// from a core or expansion set, most recent first.
+    async fn pick_canonical(conn: &mut PgConnection) -> Result<(), Error> {
+        sqlx::query(
+            r"
+            UPDATE oracle_cards o
+            SET canonical_card_id = p.id
+            FROM (
+                SELECT DISTINCT ON (c.oracle_id) c.oracle_id, c.id
+                FROM cards c
+                JOIN sets s ON s.id = c.set_id
+                WHERE c.oracle_id IS NOT NULL
+                ORDER BY
+                    c.oracle_id,
+                    c.lang = 'en' DESC,
+                    c.image_status = 'highres_scan' DESC,
+                    s.set_type IN ('core', 'expansion') DESC,
+                    c.released_at DESC,
+                    c.id
+            ) p
+            WHERE o.oracle_id = p.oracle_id AND o.canonical_card_id IS DISTINCT FROM p.id
+            ",
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn upsert_cards(
+        &mut self,
+        conn: &mut PgConnection,
+        cards: &[DbCard],
+    ) -> Result<(), Error> {
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "INSERT INTO cards ({}) ",
+            CARD_COLUMNS.join(", ")
+        ));
+        qb.push_values(cards, |mut b, c| {
+            b.push_bind(c.id)
+                .push_bind(c.oracle_id)
+                .push_bind(&c.name)
+                .push_bind(&c.lang)
+                .push_bind(c.released_at)
+                .push_bind(&c.scryfall_uri)
+                .push_bind(c.layout_id)
+                .push_bind(&c.image_status)
+                .push_bind(c.foil)
+                .push_bind(c.nonfoil)
+                .push_bind(c.oversized)
+                .push_bind(&c.rarity)
+                .push_bind(&c.artist)
+                .push_bind(c.set_id)
+                .push_bind(c.price_usd)
+                .push_bind(c.price_usd_foil)
+                .push_bind(c.price_usd_etched)
+                .push_bind(&c.edhrec_uri);
+        });
+        qb.push(on_conflict("id", CARD_COLUMNS));
+        qb.build().execute(&mut *conn).await?;
+
+        self.stats.cards += cards.len() as u64;
+        Ok(())
+    }
+
+    /// Appends the prices that differ from the latest recorded ones.
+    /// A price that was never recorded and is still missing is skipped.
+    async fn append_prices(conn: &mut PgConnection, cards: &[DbCard]) -> Result<(), Error> {
+        let prices = cards.iter().flat_map(|c| c.prices.iter().flatten());
+        let (ids, finishes, currencies, values) = prices.fold(
+            (vec![], vec![], vec![], vec![]),
+            |(mut ids, mut finishes, mut currencies, mut values), p| {
+                ids.push(p.card_id);
+                finishes.push(p.finish);
+                currencies.push(p.currency);
+                values.push(p.price);
+                (ids, finishes, currencies, values)
+            },
+        );
+
+        sqlx::query(
+            r"
+            INSERT INTO card_prices (card_id, finish, currency, price)
+            SELECT u.card_id, u.finish, u.currency, u.price
+            FROM UNNEST($1::uuid[], $2::price_finish[], $3::price_currency[], $4::real[])
+                AS u(card_id, finish, currency, price)
+            WHERE u.price IS DISTINCT FROM (
+                SELECT p.price
+                FROM card_prices p
+                WHERE p.card_id = u.card_id AND p.finish = u.finish AND p.currency = u.currency
+                ORDER BY p.observed_at DESC
+                LIMIT 1
+            )
+            ",
+        )
+        .bind(&ids)
+        .bind(&finishes)
+        .bind(&currencies)
+        .bind(&values)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn replace_keywords(
+        &mut self,
+        conn: &mut PgConnection,
+        cards: &[DbCard],
+    ) -> Result<(), Error> {
+        let names = cards.iter().flat_map(|c| c.keywords.iter().flatten());
+        lookup(
+            conn,
+            "keywords",
+            names.map(String::as_str),
+            &mut self.keywords,
+        )
+        .await?;
+
+        let (ids, keyword_ids) = cards
+            .iter()
+            .flat_map(|c| c.keywords.iter().flatten().map(move |k| (c.id, k)))
+            .filter_map(|(id, k)| self.keywords.get(k).map(|k| (id, *k)))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        replace_junction(
+            conn,
+            "card_keywords",
+            "keyword_id",
+            cards,
+            &ids,
+            &keyword_ids,
+        )
+        .await
+    }
+
+    async fn replace_finishes(
+        &mut self,
+        conn: &mut PgConnection,
+        cards: &[DbCard],
+    ) -> Result<(), Error> {
+        let names = cards.iter().flat_map(|c| c.finishes.iter().flatten());
+        lookup(
+            conn,
+            "finishes",
+            names.map(String::as_str),
+            &mut self.finishes,
+        )
+        .await?;
+
+        let (ids, finish_ids) = cards
+            .iter()
+            .flat_map(|c| c.finishes.iter().flatten().map(move |f| (c.id, f)))
+            .filter_map(|(id, f)| self.finishes.get(f).map(|f| (id, *f)))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        replace_junction(conn, "card_finishes", "finish_id", cards, &ids, &finish_ids).await
+    }
+
+    /// Updates the status of every format listed for a card, dropping formats no longer listed.
+    /// Status changes of formats the card was already listed in are recorded as events.
+    async fn replace_legalities(
+        &mut self,
+        conn: &mut PgConnection,
+        cards: &[DbCard],
+    ) -> Result<(), Error> {
+        let names = cards.iter().flat_map(|c| c.legalities.iter().flatten());
+        lookup(
+            conn,
+            "formats",
+            names.map(|(format, _)| format.as_str()),
+            &mut self.formats,
+        )
+        .await?;
+
+        let card_ids = cards.iter().map(|c| c.id).collect::<Vec<_>>();
+        let (ids, (format_ids, statuses)) = cards
+            .iter()
+            .flat_map(|c| c.legalities.iter().flatten().map(move |l| (c.id, l)))
+            .filter_map(|(id, (format, status))| {
+                self.formats.get(format).map(|f| (id, (*f, *status)))
+            })
+            .unzip::<_, _, Vec<_>, (Vec<_>, Vec<_>)>();
+
+        sqlx::query(
+            r"
+            DELETE FROM card_legalities l
+            WHERE l.card_id = ANY($1)
+                AND NOT EXISTS (
+                    SELECT 1 FROM UNNEST($2::uuid[], $3::int[]) AS u(card_id, format_id)
+                    WHERE u.card_id = l.card_id AND u.format_id = l.format_id
+                )
+            ",
+        )
+        .bind(&card_ids)
+        .bind(&ids)
+        .bind(&format_ids)
+        .execute(&mut *conn)
+        .await?;
+
+        // Recorded before the update, `NOW()` is the same for the whole sync.
+        // A status no sync wrote is a guess of the backfill, not a baseline.
+        // The history is not staged, a change already recorded by a sync that never went live
+        // is not recorded twice.
+        sqlx::query(
+            r"
+            INSERT INTO legality_changes (card_id, format, old_status, new_status)
+            SELECT u.card_id, f.name, l.status, u.status
+            FROM UNNEST($1::uuid[], $2::int[], $3::legality[]) AS u(card_id, format_id, status)
+            JOIN card_legalities l ON l.card_id = u.card_id AND l.format_id = u.format_id
+            JOIN formats f ON f.id = u.format_id
+            WHERE l.status <> u.status AND l.synced
+                AND u.status IS DISTINCT FROM (
+                    SELECT e.new_status
+                    FROM legality_changes e
+                    WHERE e.card_id = u.card_id AND e.format = f.name
+                    ORDER BY e.changed_at DESC, e.id DESC
+                    LIMIT 1
+                )
+            ",
+        )
+        .bind(&ids)
+        .bind(&format_ids)
+        .bind(&statuses)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO card_legalities (card_id, format_id, status, synced)
+            SELECT *, true FROM UNNEST($1::uuid[], $2::int[], $3::legality[])
+            ON CONFLICT (card_id, format_id) DO UPDATE
+            SET status = EXCLUDED.status, synced = true
+            WHERE card_legalities.status IS DISTINCT FROM EXCLUDED.status
+                OR NOT card_legalities.synced
+            ",
+        )
+        .bind(&ids)
+        .bind(&format_ids)
+        .bind(&statuses)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Parts are kept by id only, the related card may not be synced.
+    async fn replace_parts(conn: &mut PgConnection, cards: &[DbCard]) -> Result<(), Error> {
+        let card_ids = cards.iter().map(|c| c.id).collect::<Vec<_>>();
+        let (ids, (part_ids, (components, (names, type_lines)))) = cards
+            .iter()
+            .flat_map(|c| c.parts.iter().flatten().map(move |p| (c.id, p)))
+            .map(|(id, p)| {
+                (
+                    id,
+                    (
+                        p.part_id,
+                        (p.component, (p.name.as_str(), p.type_line.as_str())),
+                    ),
+                )
+            })
+            .unzip::<_, _, Vec<_>, (Vec<_>, (Vec<_>, (Vec<_>, Vec<_>)))>();
+
+        sqlx::query("DELETE FROM card_parts WHERE card_id = ANY($1)")
+            .bind(&card_ids)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO card_parts (card_id, part_id, component, name, type_line)
+            SELECT * FROM UNNEST($1::uuid[], $2::uuid[], $3::part_component[], $4::text[], $5::text[])
+            ",
+        )
+        .bind(&ids)
+        .bind(&part_ids)
+        .bind(&components)
+        .bind(&names)
+        .bind(&type_lines)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn replace_color_identities(
+        conn: &mut PgConnection,
+        cards: &[DbCard],
+    ) -> Result<(), Error> {
+        let card_ids = cards.iter().map(|c| c.id).collect::<Vec<_>>();
+        let (ids, codes) = cards
+            .iter()
+            .flat_map(|c| {
+                c.color_identities
+                    .iter()
+                    .flatten()
+                    .map(move |code| (c.id, code))
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        sqlx::query("DELETE FROM card_color_identity WHERE card_id = ANY($1)")
+            .bind(&card_ids)
+            .execute(&mut *conn)
+            .await?;
+
+        // Colorless is not a color, unknown codes are dropped by the join
+        sqlx::query(
+            r"
+            INSERT INTO card_color_identity (card_id, color_id)
+            SELECT u.card_id, c.id
+            FROM UNNEST($1::uuid[], $2::text[]) AS u(card_id, code)
+            JOIN colors c ON c.code = u.code
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(&ids)
+        .bind(&codes)
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn upsert_faces(
+        &mut self,
+        conn: &mut PgConnection,
+        cards: &[DbCard],
+    ) -> Result<(), Error> {
+        let faces = cards
+            .iter()
+            .flat_map(|c| c.card_faces.iter().flatten())
+            .collect::<Vec<_>>();
+
+        let mut qb = QueryBuilder::<Postgres>::new(format!(
+            "INSERT INTO card_faces ({}) ",
+            FACE_COLUMNS.join(", ")
+        ));
+        qb.push_values(&faces, |mut b, f| {
+            b.push_bind(f.card_id)
+                .push_bind(f.face_index)
+                .push_bind(&f.name)
+                .push_bind(&f.mana_cost)
+                .push_bind(f.cmc)
+                .push_bind(&f.type_line)
+                .push_bind(&f.supertypes)
+                .push_bind(&f.types)
+                .push_bind(&f.subtypes)
+                .push_bind(&f.oracle_text)
+                .push_bind(&f.flavor_text)
+                .push_bind(&f.power)
+                .push_bind(&f.toughness)
+                .push_bind(&f.loyalty)
+                .push_bind(f.power.as_ref().and_then(|s| s.value))
+                .push_bind(f.toughness.as_ref().and_then(|s| s.value))
+                .push_bind(f.loyalty.as_ref().and_then(|s| s.value))
+                .push_bind(&f.image_small)
+                .push_bind(&f.image_normal)
+                .push_bind(&f.image_large)
+                .push_bind(&f.image_png)
+                .push_bind(&f.image_art_crop)
+                .push_bind(&f.image_border_crop);
+        });
+        qb.push(on_conflict("card_id, face_index", FACE_COLUMNS));
+        qb.build().execute(&mut *conn).await?;
+
+        // Drop faces a card no longer has, this cascades to their colors
+        let (card_ids, face_counts) = cards
+            .iter()
+            .map(|c| {
+                let count = c.card_faces.as_ref().map_or(0, Vec::len);
+                (c.id, i16::try_from(count).unwrap_or(i16::MAX))
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+
+        sqlx::query(
+            r"
+            DELETE FROM card_faces f
+            USING UNNEST($1::uuid[], $2::int2[]) AS n(card_id, face_count)
+            WHERE f.card_id = n.card_id AND f.face_index >= n.face_count
+            ",
+        )
+        .bind(&card_ids)
+        .bind(&face_counts)
+        .execute(&mut *conn)
+        .await?;
+
+        let (ids, indices, codes) = faces.iter().fold(
+            (vec![], vec![], vec![]),
+            |(mut ids, mut indices, mut codes), f| {
+                for code in f.colors.iter().flatten() {
+                    ids.push(f.card_id);
+                    indices.push(f.face_index);
+                    codes.push(code.as_str());
+                }
+                (ids, indices, codes)
+            },
+        );
+
+        sqlx::query(
+            r"
+            DELETE FROM card_colors cc
+            USING card_faces f
+            WHERE cc.card_id = f.id AND f.card_id = ANY($1)
+            ",
+        )
+        .bind(&card_ids)
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query(
+            r"
+            INSERT INTO card_colors (card_id, color_id)
+            SELECT f.id, c.id
+            FROM UNNEST($1::uuid[], $2::int2[], $3::text[]) AS u(card_id, face_index, code)
+            JOIN card_faces f ON f.card_id = u.card_id AND f.face_index = u.face_index
+            JOIN colors c ON c.code = u.code
+            ON CONFLICT DO NOTHING
+            ",
+        )
+        .bind(&ids)
+        .bind(&indices)
+        .bind(&codes)
+        .execute(&mut *conn)
+        .await?;
+
+        self.stats.faces += faces.len() as u64;
+        Ok(())
+    }
+}
+
+/// Ingests all cards of the stream into `schema` in a single transaction.
+pub async fn ingest<S>(
+    conn: &mut PgConnection,
+    schema: &str,
+    cards: S,
+) -> Result<IngestStats, Error>
+where
+    S: Stream<Item = Result<Card, Error>>,
+{
+    pin_mut!(cards);
+
+    let mut tx = sqlx::Connection::begin(&mut *conn).await?;
+    // Shared enum types live in public
+    sqlx::query(&format!("SET LOCAL search_path TO {schema}, public"))
+        .execute(&mut *tx)
+        .await?;
+
+    let mut ingest = Ingest {
+        symbols: mana::known_symbols(&mut tx, schema).await?,
+        ..Ingest::default()
+    };
+    while let Some(card) = cards.try_next().await? {
+        ingest.push(&mut tx, card).await?;
+    }
+    ingest.flush(&mut tx).await?;
+    Ingest::pick_canonical(&mut tx).await?;
+
+    tx.commit().await?;
+
+    Ok(ingest.stats)
+}
+
+/// Inserts missing names into a lookup table and caches their ids.
+async fn lookup<'a, I>(
+    conn: &mut PgConnection,
+    table: &str,
+    names: I,
+    cache: &mut HashMap<String, i32>,
+) -> Result<(), Error>
+where
+    I: Iterator<Item = &'a str>,
+{
+    let mut missing = names
+        .filter(|n| !cache.contains_key(*n))
+        .collect::<Vec<_>>();
+    missing.sort_unstable();
+    missing.dedup();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    sqlx::query(&format!(
+        "INSERT INTO {table} (name) SELECT UNNEST($1::text[]) ON CONFLICT (name) DO NOTHING"
+    ))
+    .bind(&missing)
+    .execute(&mut *conn)
+    .await?;
+
+    let rows: Vec<(i32, String)> = sqlx::query_as(&format!(
+        "SELECT id, name FROM {table} WHERE name = ANY($1)"
+    ))
+    .bind(&missing)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    cache.extend(rows.into_iter().map(|(id, name)| (name, id)));
+    Ok(())
+}
+
+/// Replaces the junction rows of every card in the batch.
+async fn replace_junction(
+    conn: &mut PgConnection,
+    table: &str,
+    column: &str,
+    cards: &[DbCard],
+    ids: &[Uuid],
+    related_ids: &[i32],
+) -> Result<(), Error> {
+    let card_ids = cards.iter().map(|c| c.id).collect::<Vec<_>>();
+
+    sqlx::query(&format!("DELETE FROM {table} WHERE card_id = ANY($1)"))
+        .bind(&card_ids)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query(&format!(
+        r"
+        INSERT INTO {table} (card_id, {column})
+        SELECT * FROM UNNEST($1::uuid[], $2::int[])
+        ON CONFLICT DO NOTHING
+        "
+    ))
+    .bind(ids)
+    .bind(related_ids)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Builds an upsert clause updating every column but the conflict target.
+fn on_conflict(target: &str, columns: &[&str]) -> String {
+    let updates = columns
+        .iter()
+        .filter(|c| !target.split(", ").any(|t| t == **c))
+        .map(|c| format!("{c} = EXCLUDED.{c}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(" ON CONFLICT ({target}) DO UPDATE SET {updates}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{sync::scryfall::staging, tests::TestDb};
+
+    const LAYOUTS: [&str; 8] = [
+        "normal",
+        "meld",
+        "transform",
+        "modal_dfc",
+        "split",
+        "flip",
+        "adventure",
+        "reversible_card",
+    ];
+
+    #[tokio::test]
+    async fn test_ingest() {
+        let _t = pyre_telemetry::Telemetry::default().init_scoped();
+
+        let mut cards = vec![];
+        for layout in LAYOUTS {
+            let json = tokio::fs::read(format!("test/scryfall/{layout}.json"))
+                .await
+                .expect("Failed to read file");
+            cards.push(serde_json::from_slice::<Card>(&json).map_err(Error::from));
+        }
+
+        let db = TestDb::new().await;
+        let mut conn = db.sql.acquire().await.unwrap();
+
+        let stats = ingest(&mut conn, staging::LIVE, futures::stream::iter(cards))
+            .await
+            .unwrap();
+        assert_eq!(stats.cards, 8);
+        assert_eq!(stats.faces, 14);
+        assert_eq!(stats.unknown_symbols, 0);
+
+        let (finishes,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM scryfall.card_finishes WHERE card_id = '6a0b230b-d391-4998-a3f7-7b158a0ec2cd'",
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(finishes, 2);
+
+        let (colors,): (i64,) = sqlx::query_as(
+            r"
+            SELECT COUNT(*)
+            FROM scryfall.card_colors cc
+            JOIN scryfall.card_faces f ON f.id = cc.card_id
+            WHERE f.card_id = '2ee75dd3-7b14-4f5a-8e5c-2a3a4b0cbb69'
+            ",
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(colors, 2);
+
+        drop(conn);
+        db.close().await;
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::db;
+
+/// Status of a card in a format, as published by Scryfall
+#[derive(
+    sqlx::Type, strum::Display, Copy, Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "legality", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Legality {
+    Legal,
+    NotLegal,
+    Banned,
+    /// Limited to a single copy, e.g. the Vintage restricted list
+    Restricted,
+}
+
+impl Legality {
+    /// Restricted cards are still playable
+    pub fn is_playable(self) -> bool {
+        matches!(self, Self::Legal | Self::Restricted)
+    }
+}
+
+/// Legality by format name, formats Scryfall adds later are kept as is
+pub type Legalities = BTreeMap<String, Legality>;
+
+/// Loads the legalities of cards, keyed by card.
+pub async fn for_cards(
+    conn: &mut PgConnection,
+    ids: &[Uuid],
+) -> Result<HashMap<Uuid, Legalities>, db::Error> {
+    let rows: Vec<(Uuid, String, Legality)> = sqlx::query_as(
+        r"
+        SELECT l.card_id, f.name, l.status
+        FROM scryfall.card_legalities l
+        JOIN scryfall.formats f ON f.id = l.format_id
+        WHERE l.card_id = ANY($1)
+        ",
+    )
+    .bind(ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut legalities = HashMap::<Uuid, Legalities>::new();
+    for (card_id, format, status) in rows {
+        legalities
+            .entry(card_id)
+            .or_default()
+            .insert(format, status);
+    }
+
+    Ok(legalities)
+}
+
+/// A change of a card's status in a format, dated by the sync that saw it
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct LegalityChange {
+    pub id: i64,
+    pub card_id: Uuid,
+    /// `None` once the card is no longer synced
+    pub name: Option<String>,
+    pub format: String,
+    pub old_status: Legality,
+    pub new_status: Legality,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl LegalityChange {
+    /// Changes in a format within `[from, to]`, newest first.
+    /// `status` keeps only the changes to that status, e.g. bans.
+    ///
+    /// Changes are recorded per printing but listed once per card, under its first printing.
+    pub async fn for_format(
+        dal: sqlx::PgPool,
+        format: &str,
+        status: Option<Legality>,
+        (from, to): (DateTime<Utc>, DateTime<Utc>),
+        (limit, offset): (i64, i64),
+    ) -> Result<Vec<Self>, db::Error> {
+        Ok(sqlx::query_as(
+            r"
+            SELECT *
+            FROM (
+                -- Printings no longer synced have no oracle id left, they are kept apart
+                SELECT DISTINCT ON (COALESCE(c.oracle_id, e.card_id), e.changed_at)
+                    e.id, e.card_id, c.name, e.format, e.old_status, e.new_status, e.changed_at
+                FROM legality_changes e
+                LEFT JOIN scryfall.cards c ON c.id = e.card_id
+                WHERE e.format = $1
+                    AND ($2::legality IS NULL OR e.new_status = $2)
+                    AND e.changed_at BETWEEN $3 AND $4
+                ORDER BY COALESCE(c.oracle_id, e.card_id), e.changed_at, e.id
+            ) changes
+            ORDER BY changed_at DESC, id DESC
+            LIMIT $5 OFFSET $6
+            ",
+        )
+        .bind(format)
+        .bind(status)
+        .bind(from)
+        .bind(to)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&dal)
+        .await?)
+    }
+}
+
+/// Whether Scryfall lists a format, formats are only known once synced.
+pub async fn format_exists(dal: sqlx::PgPool, format: &str) -> Result<bool, db::Error> {
+    Ok(
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM scryfall.formats WHERE name = $1)")
+            .bind(format)
+            .fetch_one(&dal)
+            .await?,
+    )
+}
+
+/// Legalities of a card as they were at a point in time.
+///
+/// The status is the one set by the last change before `at`, or the one replaced by the first
+/// change after it, or the current one when it never changed. History starts with the first
+/// sync recording changes, earlier points in time get the status of that sync.
+pub async fn at(
+    dal: sqlx::PgPool,
+    card_id: Uuid,
+    at: DateTime<Utc>,
+) -> Result<Legalities, db::Error> {
+    let rows: Vec<(String, Legality)> = sqlx::query_as(
+        r"
+        SELECT f.name, COALESCE(
+            (
+                SELECT e.new_status
+                FROM legality_changes e
+                WHERE e.card_id = l.card_id AND e.format = f.name AND e.changed_at <= $2
+                ORDER BY e.changed_at DESC, e.id DESC
+                LIMIT 1
+            ),
+            (
+                SELECT e.old_status
+                FROM legality_changes e
+                WHERE e.card_id = l.card_id AND e.format = f.name AND e.changed_at > $2
+                ORDER BY e.changed_at, e.id
+                LIMIT 1
+            ),
+            l.status
+        )
+        FROM scryfall.card_legalities l
+        JOIN scryfall.formats f ON f.id = l.format_id
+        WHERE l.card_id = $1
+        ",
+    )
+    .bind(card_id)
+    .bind(at)
+    .fetch_all(&dal)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{card::Card, ingest, staging},
+        tests::TestDb,
+    };
+
+    async fn card(id: Uuid, oracle_id: Uuid, modern: &str) -> Card {
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        card["id"] = id.to_string().into();
+        card["oracle_id"] = oracle_id.to_string().into();
+        card["legalities"]["modern"] = modern.into();
+        serde_json::from_value(card).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_legalities() {
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: serde_json::Value = serde_json::from_slice(&json).unwrap();
+
+        // Formats Scryfall adds later deserialize without code changes
+        card["legalities"]["vintage"] = "restricted".into();
+        card["legalities"]["newformat"] = "banned".into();
+        let card: Card = serde_json::from_value(card).unwrap();
+
+        assert_eq!(card.legalities["vintage"], Legality::Restricted);
+        assert_eq!(card.legalities["newformat"], Legality::Banned);
+        assert!(card.legalities["vintage"].is_playable());
+        assert!(!card.legalities["newformat"].is_playable());
+    }
+
+    #[tokio::test]
+    async fn test_legality_changes() {
+        let db = TestDb::new().await;
+        let sql = db.sql.clone();
+        let mut conn = sql.acquire().await.unwrap();
+        // Two printings of the same card
+        let (id, reprint, oracle_id) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        // The first sync of a card is its baseline, not a change
+        for modern in ["legal", "legal", "banned"] {
+            let cards = futures::stream::iter([
+                Ok(card(id, oracle_id, modern).await),
+                Ok(card(reprint, oracle_id, modern).await),
+            ]);
+            ingest::ingest(&mut conn, staging::LIVE, cards)
+                .await
+                .unwrap();
+        }
+
+        let all = (
+            DateTime::UNIX_EPOCH,
+            Utc::now() + chrono::TimeDelta::days(1),
+        );
+        let bans = LegalityChange::for_format(
+            sql.clone(),
+            "modern",
+            Some(Legality::Banned),
+            all,
+            (500, 0),
+        )
+        .await
+        .unwrap();
+        let banned = bans
+            .iter()
+            .filter(|c| c.card_id == id || c.card_id == reprint)
+            .collect::<Vec<_>>();
+        assert_eq!(banned.len(), 1, "listed once per card");
+        let ban = banned[0];
+        assert_eq!(ban.old_status, Legality::Legal);
+        assert_eq!(ban.name.as_deref(), Some("Llanowar Elves"));
+
+        let unbans =
+            LegalityChange::for_format(sql.clone(), "modern", Some(Legality::Legal), all, (500, 0))
+                .await
+                .unwrap();
+        assert!(unbans
+            .iter()
+            .all(|c| c.card_id != id && c.card_id != reprint));
+
+        let before = at(sql.clone(), id, ban.changed_at - chrono::TimeDelta::days(1))
+            .await
+            .unwrap();
+        assert_eq!(before["modern"], Legality::Legal);
+        let after = at(sql.clone(), id, Utc::now()).await.unwrap();
+        assert_eq!(after["modern"], Legality::Banned);
+        assert_eq!(after["legacy"], before["legacy"]);
+
+        // Backfilled statuses are replaced without recording a change
+        sqlx::query(
+            "UPDATE scryfall.card_legalities SET status = 'not_legal', synced = false WHERE card_id = $1",
+        )
+        .bind(id)
+        .execute(&sql)
+        .await
+        .unwrap();
+        let cards = futures::stream::iter([Ok(card(id, oracle_id, "banned").await)]);
+        ingest::ingest(&mut conn, staging::LIVE, cards)
+            .await
+            .unwrap();
+        let changes: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM legality_changes WHERE card_id = $1")
+                .bind(id)
+                .fetch_one(&sql)
+                .await
+                .unwrap();
+        assert_eq!(changes, 1);
+
+        assert!(format_exists(sql.clone(), "modern").await.unwrap());
+        assert!(!format_exists(sql.clone(), "nonexistent").await.unwrap());
+
+        drop(conn);
+        db.close().await;
+    }
+}
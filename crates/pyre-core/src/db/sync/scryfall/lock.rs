@@ -0,0 +1,216 @@
+use std::{str::FromStr, time::Duration};
+
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgConnectOptions, Connection, PgConnection};
+use tracing::{info, warn};
+
+use super::Error;
+use crate::db::sync::config::LockConfig;
+
+/// Advisory lock key of dbsync, "pyresync" in ASCII. Also taken by the migrations.
+pub(crate) const LOCK_KEY: i64 = 0x7079_7265_7379_6e63;
+
+/// The connection holding the lock of another dbsync
+#[derive(sqlx::FromRow, Debug, Clone)]
+pub struct Holder {
+    pub pid: i32,
+    /// The owner of the other dbsync, see [`SyncLock::owner`]
+    pub application_name: String,
+    pub client_addr: Option<String>,
+    pub backend_start: Option<DateTime<Utc>>,
+}
+
+impl std::fmt::Display for Holder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} (backend {}", self.application_name, self.pid)?;
+        if let Some(addr) = &self.client_addr {
+            write!(f, " from {addr}")?;
+        }
+        if let Some(start) = self.backend_start {
+            write!(f, " since {start}")?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Session advisory lock making sure a single dbsync writes at a time.
+///
+/// Held by a dedicated connection, the lock goes away with it when the process dies.
+#[derive(Debug)]
+pub struct SyncLock {
+    conn: PgConnection,
+    /// Backend of `conn`, the one holding the lock
+    pid: i32,
+    /// Host and process of this dbsync, shown to the ones waiting
+    pub owner: String,
+}
+
+impl SyncLock {
+    /// Takes the lock, waiting for the holder to release it when configured to.
+    /// Returns `None` on shutdown while waiting.
+    pub async fn acquire(
+        pg: &str,
+        cfg: &LockConfig,
+        shutdown: &mut tokio::sync::broadcast::Receiver<()>,
+    ) -> Result<Option<Self>, Error> {
+        let owner = owner();
+        let opts = PgConnectOptions::from_str(pg)?.application_name(&owner);
+        let mut conn = PgConnection::connect_with(&opts).await?;
+
+        loop {
+            let locked: bool = sqlx::query_scalar("SELECT pg_try_advisory_lock($1)")
+                .bind(LOCK_KEY)
+                .fetch_one(&mut conn)
+                .await?;
+
+            if locked {
+                let pid = sqlx::query_scalar("SELECT pg_backend_pid()")
+                    .fetch_one(&mut conn)
+                    .await?;
+                info!(gauge.dbsync_lock_held = 1, %owner, "acquired sync lock");
+                return Ok(Some(Self { conn, pid, owner }));
+            }
+
+            let holder = holder(&mut conn)
+                .await?
+                .map_or_else(|| "unknown".to_string(), |h| h.to_string());
+
+            if !cfg.wait {
+                return Err(Error::Locked(holder));
+            }
+
+            warn!(
+                gauge.dbsync_lock_held = 0,
+                %owner,
+                %holder,
+                retry = cfg.retry,
+                "sync lock held by another dbsync, waiting"
+            );
+
+            tokio::select! {
+                _ = shutdown.recv() => return Ok(None),
+                () = tokio::time::sleep(Duration::from_secs(cfg.retry)) => {}
+            }
+        }
+    }
+
+    /// Makes sure the lock is still held, it is lost with the connection.
+    pub async fn check(&mut self) -> Result<(), Error> {
+        match holder(&mut self.conn).await {
+            Ok(holder) => Self::held(holder, self.pid),
+            Err(Error::Database(e)) => Err(Error::LockLost(e)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Makes sure the lock is still held from another connection, such as the transaction
+    /// relying on it.
+    pub async fn verify(&self, conn: &mut PgConnection) -> Result<(), Error> {
+        Self::held(holder(conn).await?, self.pid)
+    }
+
+    fn held(holder: Option<Holder>, pid: i32) -> Result<(), Error> {
+        match holder {
+            Some(holder) if holder.pid == pid => Ok(()),
+            Some(holder) => Err(Error::Locked(holder.to_string())),
+            None => Err(Error::LockNotHeld),
+        }
+    }
+
+    pub async fn release(mut self) -> Result<(), Error> {
+        sqlx::query("SELECT pg_advisory_unlock($1)")
+            .bind(LOCK_KEY)
+            .execute(&mut self.conn)
+            .await?;
+        self.conn.close().await?;
+
+        info!(gauge.dbsync_lock_held = 0, owner = %self.owner, "released sync lock");
+        Ok(())
+    }
+}
+
+/// The connection holding the lock, `None` when it is free.
+pub async fn holder(conn: &mut PgConnection) -> Result<Option<Holder>, Error> {
+    // A bigint key is split over classid and objid
+    Ok(sqlx::query_as(
+        r"
+        SELECT a.pid, a.application_name, host(a.client_addr) AS client_addr, a.backend_start
+        FROM pg_locks l
+        JOIN pg_stat_activity a ON a.pid = l.pid
+        WHERE l.locktype = 'advisory'
+            AND l.granted
+            AND l.objsubid = 1
+            AND ((l.classid::bigint << 32) | l.objid::bigint) = $1
+        ",
+    )
+    .bind(LOCK_KEY)
+    .fetch_optional(&mut *conn)
+    .await?)
+}
+
+/// e.g. `pyre-dbsync pyre-dbsync-7d9f-x2x4:1234`, the pod name on k8s
+fn owner() -> String {
+    let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "unknown".to_string());
+    format!("pyre-dbsync {host}:{}", std::process::id())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::sync::scryfall::staging::tests::SYNC, svc::state::DbConfig};
+
+    #[tokio::test]
+    async fn test_sync_lock() {
+        let _sync = SYNC.lock().await;
+        let pg = DbConfig::default().pg;
+        let (_tx, mut shutdown) = tokio::sync::broadcast::channel(1);
+
+        let cfg = LockConfig {
+            wait: false,
+            retry: 1,
+        };
+        let mut lock = SyncLock::acquire(&pg, &cfg, &mut shutdown)
+            .await
+            .unwrap()
+            .unwrap();
+
+        let err = SyncLock::acquire(&pg, &cfg, &mut shutdown)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, Error::Locked(holder) if holder.contains(&lock.owner)),
+            "{err}"
+        );
+
+        // A waiting dbsync stops on shutdown
+        let (tx, mut shutdown) = tokio::sync::broadcast::channel(1);
+        let waiting = tokio::spawn(async move {
+            let cfg = LockConfig {
+                wait: true,
+                retry: 60,
+            };
+            SyncLock::acquire(&pg, &cfg, &mut shutdown).await
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        tx.send(()).unwrap();
+        assert!(waiting.await.unwrap().unwrap().is_none());
+
+        // Verified from the connection swapping the data
+        let mut conn = PgConnection::connect(&DbConfig::default().pg)
+            .await
+            .unwrap();
+        lock.verify(&mut conn).await.unwrap();
+
+        // The lock goes away with its connection
+        sqlx::query("SELECT pg_terminate_backend($1, 5000)")
+            .bind(lock.pid)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        assert!(matches!(
+            lock.verify(&mut conn).await,
+            Err(Error::LockNotHeld)
+        ));
+        assert!(matches!(lock.check().await, Err(Error::LockLost(_))));
+    }
+}
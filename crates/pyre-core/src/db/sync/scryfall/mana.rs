@@ -0,0 +1,419 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+
+use crate::db;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManaError {
+    #[error("malformed mana cost: {0}")]
+    Malformed(String),
+
+    #[error("unknown mana symbol: {0}")]
+    UnknownSymbol(String),
+}
+
+/// The five colors, ordered WUBRG
+#[derive(
+    strum::Display,
+    strum::EnumString,
+    Copy,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+pub enum Color {
+    W,
+    U,
+    B,
+    R,
+    G,
+}
+
+/// A single `{...}` symbol of a mana cost
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Symbol {
+    /// `{3}`
+    Generic { amount: u32 },
+    /// `{W}`
+    Colored { color: Color },
+    /// `{C}`
+    Colorless,
+    /// `{W/U}`
+    Hybrid { first: Color, second: Color },
+    /// `{2/W}`, pays either the generic amount or the color
+    GenericHybrid { amount: u32, color: Color },
+    /// `{C/W}`
+    ColorlessHybrid { color: Color },
+    /// `{W/P}`, pays the color or two life
+    Phyrexian { color: Color },
+    /// `{G/W/P}`
+    HybridPhyrexian { first: Color, second: Color },
+    /// `{S}`
+    Snow,
+    /// `{X}`, `{Y}` or `{Z}`
+    Variable { name: char },
+    /// `{½}` or `{HW}` from Un-sets
+    Half { color: Option<Color> },
+}
+
+impl Symbol {
+    /// Mana value of the symbol, variables count as zero
+    pub fn mana_value(self) -> f64 {
+        match self {
+            Self::Generic { amount } | Self::GenericHybrid { amount, .. } => f64::from(amount),
+            Self::Variable { .. } => 0.0,
+            Self::Half { .. } => 0.5,
+            _ => 1.0,
+        }
+    }
+
+    /// Colors the symbol can be paid with
+    pub fn colors(self) -> Vec<Color> {
+        match self {
+            Self::Colored { color }
+            | Self::GenericHybrid { color, .. }
+            | Self::ColorlessHybrid { color }
+            | Self::Phyrexian { color }
+            | Self::Half { color: Some(color) } => vec![color],
+            Self::Hybrid { first, second } | Self::HybridPhyrexian { first, second } => {
+                vec![first, second]
+            }
+            _ => vec![],
+        }
+    }
+
+    pub fn is_hybrid(self) -> bool {
+        matches!(
+            self,
+            Self::Hybrid { .. }
+                | Self::GenericHybrid { .. }
+                | Self::ColorlessHybrid { .. }
+                | Self::HybridPhyrexian { .. }
+        )
+    }
+
+    pub fn is_phyrexian(self) -> bool {
+        matches!(self, Self::Phyrexian { .. } | Self::HybridPhyrexian { .. })
+    }
+
+    /// Parses the inner text of a symbol, e.g. `W/U` for `{W/U}`.
+    fn parse(inner: &str) -> Result<Self, ManaError> {
+        let color = |s: &str| s.parse::<Color>().ok();
+        let unknown = || ManaError::UnknownSymbol(format!("{{{inner}}}"));
+
+        let symbol = match inner.split('/').collect::<Vec<_>>()[..] {
+            ["C"] => Self::Colorless,
+            ["S"] => Self::Snow,
+            ["½"] => Self::Half { color: None },
+            [name @ ("X" | "Y" | "Z")] => {
+                Self::Variable {
+                    name: name.chars().next().ok_or_else(unknown)?,
+                }
+            }
+            [single] => {
+                if let Some(color) = color(single) {
+                    Self::Colored { color }
+                } else if let Some(half) = single.strip_prefix('H') {
+                    Self::Half {
+                        color: Some(color(half).ok_or_else(unknown)?),
+                    }
+                } else {
+                    Self::Generic {
+                        amount: single.parse().map_err(|_| unknown())?,
+                    }
+                }
+            }
+            [first, "P"] => {
+                Self::Phyrexian {
+                    color: color(first).ok_or_else(unknown)?,
+                }
+            }
+            [first, second, "P"] => {
+                Self::HybridPhyrexian {
+                    first: color(first).ok_or_else(unknown)?,
+                    second: color(second).ok_or_else(unknown)?,
+                }
+            }
+            ["C", second] => {
+                Self::ColorlessHybrid {
+                    color: color(second).ok_or_else(unknown)?,
+                }
+            }
+            [first, second] => {
+                let second = color(second).ok_or_else(unknown)?;
+                match (color(first), first.parse()) {
+                    (Some(first), _) => Self::Hybrid { first, second },
+                    (None, Ok(amount)) => {
+                        Self::GenericHybrid {
+                            amount,
+                            color: second,
+                        }
+                    }
+                    (None, Err(_)) => return Err(unknown()),
+                }
+            }
+            _ => return Err(unknown()),
+        };
+
+        Ok(symbol)
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Generic { amount } => write!(f, "{{{amount}}}"),
+            Self::Colored { color } => write!(f, "{{{color}}}"),
+            Self::Colorless => f.write_str("{C}"),
+            Self::Hybrid { first, second } => write!(f, "{{{first}/{second}}}"),
+            Self::GenericHybrid { amount, color } => write!(f, "{{{amount}/{color}}}"),
+            Self::ColorlessHybrid { color } => write!(f, "{{C/{color}}}"),
+            Self::Phyrexian { color } => write!(f, "{{{color}/P}}"),
+            Self::HybridPhyrexian { first, second } => write!(f, "{{{first}/{second}/P}}"),
+            Self::Snow => f.write_str("{S}"),
+            Self::Variable { name } => write!(f, "{{{name}}}"),
+            Self::Half { color: None } => f.write_str("{½}"),
+            Self::Half { color: Some(color) } => write!(f, "{{H{color}}}"),
+        }
+    }
+}
+
+/// A mana cost such as `{2}{W/U}{G/P}`, split into its symbols
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManaCost {
+    pub symbols: Vec<Symbol>,
+}
+
+impl ManaCost {
+    /// Sum of the mana value of every symbol, `X` counts as zero
+    pub fn mana_value(&self) -> f64 {
+        self.symbols.iter().map(|s| s.mana_value()).sum()
+    }
+
+    /// Colors appearing in the cost, in WUBRG order
+    pub fn colors(&self) -> Vec<Color> {
+        self.pips().into_keys().collect()
+    }
+
+    /// Colored symbols by color, a hybrid symbol counts for each of its colors
+    pub fn pips(&self) -> BTreeMap<Color, u32> {
+        let mut pips = BTreeMap::new();
+        for color in self.symbols.iter().flat_map(|s| s.colors()) {
+            *pips.entry(color).or_default() += 1;
+        }
+        pips
+    }
+
+    pub fn has_hybrid(&self) -> bool {
+        self.symbols.iter().any(|s| s.is_hybrid())
+    }
+
+    pub fn has_phyrexian(&self) -> bool {
+        self.symbols.iter().any(|s| s.is_phyrexian())
+    }
+
+    /// Checks that every symbol is one Scryfall publishes, see [`known_symbols`].
+    pub fn validate(&self, known: &HashSet<String>) -> Result<(), ManaError> {
+        match self
+            .symbols
+            .iter()
+            .map(ToString::to_string)
+            .find(|s| !known.contains(s))
+        {
+            Some(symbol) => Err(ManaError::UnknownSymbol(symbol)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FromStr for ManaCost {
+    type Err = ManaError;
+
+    fn from_str(cost: &str) -> Result<Self, Self::Err> {
+        let malformed = || ManaError::Malformed(cost.to_string());
+
+        let mut symbols = vec![];
+        let mut rest = cost.trim();
+        while !rest.is_empty() {
+            let inner = rest.strip_prefix('{').ok_or_else(malformed)?;
+            let (inner, tail) = inner.split_once('}').ok_or_else(malformed)?;
+            if inner.contains('{') {
+                return Err(malformed());
+            }
+
+            symbols.push(Symbol::parse(inner)?);
+            rest = tail;
+        }
+
+        Ok(Self { symbols })
+    }
+}
+
+impl std::fmt::Display for ManaCost {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.symbols.iter().try_for_each(|s| write!(f, "{s}"))
+    }
+}
+
+/// The symbols synced from the Scryfall symbology into `schema`, e.g. `{W/U}`.
+pub async fn known_symbols(
+    conn: &mut PgConnection,
+    schema: &str,
+) -> Result<HashSet<String>, db::Error> {
+    let symbols: Vec<String> = sqlx::query_scalar(&format!("SELECT symbol FROM {schema}.symbols"))
+        .fetch_all(conn)
+        .await?;
+
+    Ok(symbols.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{
+            staging,
+            symbology::{self, SymbolList},
+        },
+        tests::TestDb,
+    };
+
+    fn cost(cost: &str) -> ManaCost {
+        cost.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let parsed = cost("{2}{W/U}{G/P}{S}{X}{C}{2/B}{C/R}{G/W/P}{HW}{½}");
+        assert_eq!(
+            parsed.symbols,
+            vec![
+                Symbol::Generic { amount: 2 },
+                Symbol::Hybrid {
+                    first: Color::W,
+                    second: Color::U
+                },
+                Symbol::Phyrexian { color: Color::G },
+                Symbol::Snow,
+                Symbol::Variable { name: 'X' },
+                Symbol::Colorless,
+                Symbol::GenericHybrid {
+                    amount: 2,
+                    color: Color::B
+                },
+                Symbol::ColorlessHybrid { color: Color::R },
+                Symbol::HybridPhyrexian {
+                    first: Color::G,
+                    second: Color::W
+                },
+                Symbol::Half {
+                    color: Some(Color::W)
+                },
+                Symbol::Half { color: None },
+            ]
+        );
+        assert_eq!(
+            parsed.to_string(),
+            "{2}{W/U}{G/P}{S}{X}{C}{2/B}{C/R}{G/W/P}{HW}{½}"
+        );
+        assert!(parsed.has_hybrid());
+        assert!(parsed.has_phyrexian());
+
+        assert!(cost("").symbols.is_empty());
+        assert!(!cost("{1}{G}").has_hybrid());
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(matches!(
+            "{2}{W".parse::<ManaCost>(),
+            Err(ManaError::Malformed(_))
+        ));
+        assert!(matches!(
+            "2{W}".parse::<ManaCost>(),
+            Err(ManaError::Malformed(_))
+        ));
+        assert!(matches!(
+            "{{W}}".parse::<ManaCost>(),
+            Err(ManaError::Malformed(_))
+        ));
+        assert!(matches!(
+            "{Q}".parse::<ManaCost>(),
+            Err(ManaError::UnknownSymbol(_))
+        ));
+        assert!(matches!(
+            "{W/Q}".parse::<ManaCost>(),
+            Err(ManaError::UnknownSymbol(_))
+        ));
+        assert!(matches!(
+            "{-1}".parse::<ManaCost>(),
+            Err(ManaError::UnknownSymbol(_))
+        ));
+    }
+
+    #[test]
+    fn test_mana_value() {
+        assert!((cost("{2}{W/U}{G/P}").mana_value() - 4.0).abs() < f64::EPSILON);
+        assert!((cost("{X}{X}{R}").mana_value() - 1.0).abs() < f64::EPSILON);
+        assert!((cost("{2/W}{2/W}").mana_value() - 4.0).abs() < f64::EPSILON);
+        assert!((cost("{HW}").mana_value() - 0.5).abs() < f64::EPSILON);
+        assert!((cost("{10}{C}{S}").mana_value() - 12.0).abs() < f64::EPSILON);
+        assert!(cost("").mana_value().abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_pips() {
+        assert_eq!(
+            cost("{G}{1}{W/U}").colors(),
+            vec![Color::W, Color::U, Color::G]
+        );
+        assert_eq!(
+            cost("{2/B}{R/P}{HW}").colors(),
+            vec![Color::W, Color::B, Color::R]
+        );
+        assert!(cost("{3}{C}").colors().is_empty());
+
+        let pips = cost("{1}{W}{W}{W/U}{U/P}");
+        assert_eq!(pips.pips(), BTreeMap::from([(Color::W, 3), (Color::U, 2)]));
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let json = tokio::fs::read("test/scryfall/symbology.json")
+            .await
+            .expect("Failed to read file");
+        let symbols: SymbolList = serde_json::from_slice(&json).unwrap();
+
+        let db = TestDb::new().await;
+        let mut conn = PgConnection::connect(&db.pg).await.unwrap();
+        symbology::upsert(&mut conn, staging::LIVE, symbols)
+            .await
+            .unwrap();
+
+        let known = known_symbols(&mut conn, staging::LIVE).await.unwrap();
+        assert!(cost("{X}{2}{W/U}{G/P}").validate(&known).is_ok());
+
+        // Well formed, but Scryfall only publishes hybrids in color order
+        assert!(matches!(
+            cost("{U/W}").validate(&known),
+            Err(ManaError::UnknownSymbol(s)) if s == "{U/W}"
+        ));
+
+        conn.close().await.unwrap();
+        db.close().await;
+    }
+}
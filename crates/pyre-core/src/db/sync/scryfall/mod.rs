@@ -1,16 +1,54 @@
 use std::time::Duration;
 
 use bulk::{BulkMetadata, BulkMetadataList};
-use hyper::{header::ACCEPT, HeaderMap, StatusCode};
+use card::Card;
+use client::Client;
+use db_card::DbSet;
+use diff::DiffReport;
+use futures::{future, Stream, StreamExt, TryStreamExt};
+use hyper::StatusCode;
+use ingest::IngestStats;
+use lock::SyncLock;
+use run::{Outcome, SyncRun};
 use serde::de::DeserializeOwned;
-use sqlx::{Connection, PgConnection};
-use tracing::{error, info};
+use set::SetList;
+use sqlx::{PgConnection, PgPool};
+use tracing::{error, info, warn};
 
-use super::config;
+use super::{
+    config::{self, FilterConfig, SyncConfig},
+    Api,
+    Options,
+};
+use crate::db::{self, Dao};
 
 pub mod bulk;
 pub mod card;
+pub mod client;
 pub mod db_card;
+pub mod diff;
+pub mod face;
+pub mod file;
+pub mod filter;
+pub mod image;
+pub mod ingest;
+pub mod legality;
+pub mod lock;
+pub mod mana;
+pub mod oracle;
+pub mod part;
+pub mod price;
+pub mod ruling;
+pub mod run;
+pub mod set;
+pub mod staging;
+pub mod stat;
+pub mod stream;
+pub mod symbology;
+pub mod type_line;
+
+const DEFAULT_CARDS: &str = "default_cards";
+const RULINGS: &str = "rulings";
 
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -22,96 +60,337 @@ pub enum Error {
         status: StatusCode,
         response: String,
     },
+
+    #[error("failed to parse scryfall json: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("invalid scryfall id: {0}")]
+    InvalidId(#[from] uuid::Error),
+
+    #[error("failed to read scryfall bulk file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("malformed scryfall bulk file: {0}")]
+    Bulk(&'static str),
+
+    #[error("scryfall bulk data not found: {0}")]
+    MissingBulk(&'static str),
+
+    #[error("failed to write scryfall data: {0}")]
+    Database(#[from] sqlx::Error),
+
+    #[error(transparent)]
+    Dao(#[from] db::Error),
+
+    #[error("failed to store card image: {0}")]
+    Image(#[from] pyre_fs::cas::Error),
+
+    #[error("staged scryfall data failed integrity checks: {0}")]
+    Integrity(String),
+
+    #[error("no previous scryfall data to roll back to")]
+    NoPrevious,
+
+    #[error("another dbsync is running: {0}")]
+    Locked(String),
+
+    #[error("lost the sync lock: {0}")]
+    LockLost(sqlx::Error),
+
+    #[error("the sync lock is no longer held")]
+    LockNotHeld,
 }
 
 #[derive(Debug)]
 pub struct ScryfallSync {
     pub cfg: config::Config,
-    pub url: String,
-    pub path: String,
+    pub opts: Options,
     pub shutdown: tokio::sync::broadcast::Receiver<()>,
 }
 
 impl ScryfallSync {
     pub fn new(
         cfg: config::Config,
-        url: String,
-        path: String,
+        opts: Options,
         shutdown: tokio::sync::broadcast::Receiver<()>,
     ) -> Self {
         Self {
             cfg,
-            url,
-            path,
+            opts,
             shutdown,
         }
     }
 
     pub async fn start(&mut self) -> color_eyre::Result<()> {
-        let url = format!("{}/{}", self.url, self.path);
-        let user_agent: String =
-            format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        let sql = PgPool::connect(&self.cfg.db.pg).await?;
+        let client = Client::new(self.cfg.sync.client.clone())?;
 
-        let _sql = PgConnection::connect(&self.cfg.db.pg).await?;
+        if self.opts.dry_run {
+            self.dry_run(&client, &sql).await?;
+            sql.close().await;
+            return Ok(());
+        }
 
-        let mut headers = HeaderMap::new();
-        headers.insert(ACCEPT, "application/json".parse().unwrap());
+        let Some(mut lock) =
+            SyncLock::acquire(&self.cfg.db.pg, &self.cfg.sync.lock, &mut self.shutdown).await?
+        else {
+            info!("scryfall sync shutting down before taking the sync lock");
+            sql.close().await;
+            return Ok(());
+        };
 
-        let client = reqwest::Client::builder()
-            .http2_prior_knowledge()
-            .connect_timeout(Duration::from_secs(3))
-            .timeout(Duration::from_secs(3))
-            .user_agent(user_agent)
-            .default_headers(headers)
-            .build()
-            .unwrap();
+        if self.opts.rollback {
+            staging::rollback(&mut *sql.acquire().await?).await?;
 
-        let bulk = Self::get_bulk(&client, &url).await?;
-        info!(%bulk, "downloaded bulk metadata");
+            // The run history is not rolled back, the rollback gets a data version of its own
+            let mut run = SyncRun::new();
+            run.finish(Outcome::Rollback, None, None);
+            run.create(sql.clone()).await?;
+            run.update(sql.clone()).await?;
+            info!(run = run.id, "rolled back scryfall data");
 
-        // Filter MTGO only cards (with games[] with mtgo only)
-        // Populate symbology table using /symbology
-        // Card faces should always be present even with a single card face (card_faces null or empty)
-        // Sometimes card faces is present, but the images are in the original card object, so move them over
+            SyncRun::notify(sql.clone(), run.id).await?;
+            lock.release().await?;
+            sql.close().await;
+            return Ok(());
+        }
+
+        loop {
+            lock.check().await?;
+            let outcome = self.run(&client, &sql, &lock).await?;
 
-        // loop {
-        //     tokio::select! {
-        //         _ = self.shutdown.recv() => {
-        //             info!("scryfall sync shutting down");
-        //             break;
-        //         }
-        //         () = tokio::time::sleep(tokio::time::Duration::from_secs(self.cfg.sync.freq)) => {
-        //             info!("scryfall sync running");
+            if outcome == Outcome::Cancelled || self.opts.once {
+                break;
+            }
 
-        //         }
-        //     }
-        // }
+            tokio::select! {
+                _ = self.shutdown.recv() => {
+                    info!("scryfall sync shutting down");
+                    break;
+                }
+                () = tokio::time::sleep(Duration::from_secs(self.cfg.sync.freq)) => {
+                    info!("scryfall sync running");
+                }
+            }
+        }
 
+        lock.release().await?;
+        sql.close().await;
         Ok(())
     }
 
-    async fn get_bulk(client: &reqwest::Client, url: &str) -> color_eyre::Result<BulkMetadata> {
-        let bulk_data = Self::download::<BulkMetadataList>(client, url).await?;
-        Ok(bulk_data
-            .data
-            .into_iter()
-            .find(|b| b.bulk_type == "default_cards")
-            .expect("default_cards bulk data not found"))
+    /// Compares the source with the database and reports the differences, nothing is written.
+    async fn dry_run(&self, client: &Client, sql: &PgPool) -> Result<DiffReport, Error> {
+        let filter = &self.cfg.sync.filter;
+        let mut conn = sql.acquire().await?;
+
+        let report = match &self.cfg.sync.api {
+            Api::Scryfall { url, path } => {
+                let bulks = client
+                    .json::<BulkMetadataList>(&format!("{url}/{path}"))
+                    .await?;
+                let bulk = bulks.find(DEFAULT_CARDS)?;
+                info!(%bulk, "downloaded bulk metadata");
+
+                let sets = client.json::<SetList>(&format!("{url}/sets")).await?;
+                let sets = sets.data.into_iter().map(DbSet::from).collect();
+
+                let cards = Self::get_array::<Card>(client, bulk)
+                    .try_filter(|card| future::ready(filter.matches(card)));
+                diff::diff(&mut conn, Some(sets), cards).await?
+            }
+            Api::LocalFile { path } => {
+                let cards = file::open(path)
+                    .await?
+                    .try_filter(|card| future::ready(filter.matches(card)));
+                diff::diff(&mut conn, None, cards).await?
+            }
+        };
+
+        info!(%report, "dry run finished, nothing was written");
+
+        if let Some(path) = &self.opts.diff {
+            tokio::fs::write(path, serde_json::to_vec_pretty(&report)?).await?;
+            info!(path = %path.display(), entries = report.entries.len(), "wrote dry run diff");
+        }
+
+        Ok(report)
     }
 
-    async fn download<T>(client: &reqwest::Client, url: &str) -> Result<T, Error>
+    /// Runs a single sync, recording it in `sync_runs`.
+    /// Failures are recorded and logged, only database errors on the run record itself are fatal.
+    async fn run(
+        &mut self,
+        client: &Client,
+        sql: &PgPool,
+        lock: &SyncLock,
+    ) -> Result<Outcome, Error> {
+        let mut run = SyncRun::new();
+        run.create(sql.clone()).await?;
+
+        let result = tokio::select! {
+            res = Self::sync(client, sql, &self.cfg.sync, &mut run, lock) => res.map(Some),
+            _ = self.shutdown.recv() => Ok(None),
+        };
+
+        match result {
+            Ok(Some(Some(stats))) => run.finish(Outcome::Success, Some(stats), None),
+            Ok(Some(None)) => run.finish(Outcome::Skipped, None, None),
+            // Once swapped the new data is live, whatever happens after
+            Ok(None) if run.outcome == Outcome::Success => {
+                warn!(run = run.id, "scryfall sync cancelled after going live");
+            }
+            Err(e) if run.outcome == Outcome::Success => {
+                error!(run = run.id, %e, "scryfall sync failed after going live");
+                run.error = Some(e.to_string());
+            }
+            Ok(None) => {
+                warn!(run = run.id, "scryfall sync cancelled, rolled back");
+                run.finish(Outcome::Cancelled, None, None);
+            }
+            Err(e) => {
+                error!(run = run.id, %e, "scryfall sync failed");
+                run.finish(Outcome::Failed, None, Some(e.to_string()));
+            }
+        }
+
+        if matches!(run.outcome, Outcome::Failed | Outcome::Cancelled) {
+            let mut conn = sql.acquire().await?;
+            if let Err(e) = staging::discard(&mut conn).await {
+                warn!(run = run.id, %e, "failed to drop the staging schema");
+            }
+        }
+
+        run.update(sql.clone()).await?;
+        info!(run = run.id, outcome = %run.outcome, cards = run.cards, "scryfall sync finished");
+
+        if run.outcome == Outcome::Success {
+            SyncRun::notify(sql.clone(), run.id).await?;
+        }
+
+        Ok(run.outcome)
+    }
+
+    async fn sync(
+        client: &Client,
+        sql: &PgPool,
+        cfg: &SyncConfig,
+        run: &mut SyncRun,
+        lock: &SyncLock,
+    ) -> Result<Option<IngestStats>, Error> {
+        let stats = match &cfg.api {
+            Api::Scryfall { url, path } => {
+                Self::sync_api(client, sql, &cfg.filter, url, path, run, lock).await?
+            }
+            Api::LocalFile { path } => {
+                info!(path = %path.display(), "reading local bulk file");
+                let mut conn = sql.acquire().await?;
+                let staged = staging::prepare(&mut conn).await?;
+
+                let cards = file::open(path).await?;
+                let stats = Self::ingest(&mut conn, &cfg.filter, cards).await?;
+
+                Self::publish(&mut conn, &staged, lock, run, stats).await?;
+                Some(stats)
+            }
+        };
+
+        // Also runs when the bulk is unchanged, to retry images that failed to download
+        if cfg.images.enabled {
+            let images = image::mirror(client, sql, &cfg.images).await?;
+            info!(images, "mirrored card images");
+        }
+
+        Ok(stats)
+    }
+
+    /// Ingests the bulk file if it changed since the last successful run.
+    /// Everything is written to the staging schema, which only goes live once complete.
+    async fn sync_api(
+        client: &Client,
+        sql: &PgPool,
+        filter: &FilterConfig,
+        url: &str,
+        path: &str,
+        run: &mut SyncRun,
+        lock: &SyncLock,
+    ) -> Result<Option<IngestStats>, Error> {
+        let bulks = client
+            .json::<BulkMetadataList>(&format!("{url}/{path}"))
+            .await?;
+        let bulk = bulks.find(DEFAULT_CARDS)?;
+        let rulings = bulks.find(RULINGS)?;
+        info!(%bulk, "downloaded bulk metadata");
+        run.with_bulk(bulk)?;
+
+        let last = SyncRun::last_success(sql.clone()).await?;
+        if last.is_some_and(|last| last >= bulk.updated_at) {
+            info!(updated_at = %bulk.updated_at, "scryfall bulk unchanged, skipping");
+            return Ok(None);
+        }
+
+        let mut conn = sql.acquire().await?;
+        let staged = staging::prepare(&mut conn).await?;
+
+        let symbols = client.json(&format!("{url}/symbology")).await?;
+        let symbols = symbology::upsert(&mut conn, staging::NEXT, symbols).await?;
+        info!(symbols, "synced symbology");
+
+        let sets = client.json(&format!("{url}/sets")).await?;
+        let sets = set::upsert(&mut conn, staging::NEXT, sets).await?;
+        info!(sets, "synced sets");
+
+        let cards = Self::get_array(client, bulk);
+        let mut stats = Self::ingest(&mut conn, filter, cards).await?;
+
+        let rulings = Self::get_array(client, rulings);
+        stats.rulings = ruling::ingest(&mut conn, staging::NEXT, rulings).await?;
+        info!(rulings = stats.rulings, "ingested bulk rulings");
+
+        Self::publish(&mut conn, &staged, lock, run, stats).await?;
+        Ok(Some(stats))
+    }
+
+    /// Ingests the cards matching the filter into the staging schema in a single transaction.
+    async fn ingest<S>(
+        conn: &mut PgConnection,
+        filter: &FilterConfig,
+        cards: S,
+    ) -> Result<IngestStats, Error>
     where
-        T: DeserializeOwned,
+        S: Stream<Item = Result<Card, Error>>,
     {
-        let res = client.get(url).send().await?;
+        let cards = cards.try_filter(|card| future::ready(filter.matches(card)));
+        let stats = ingest::ingest(conn, staging::NEXT, cards).await?;
+        info!(%stats, "ingested bulk cards");
 
-        if res.status() != 200 {
-            return Err(Error::Scryfall {
-                status: res.status(),
-                response: res.text().await?,
-            });
-        }
+        Ok(stats)
+    }
+
+    /// Checks the staged data and swaps it live, the live data is untouched if it fails.
+    /// The run is a success as soon as the swap commits, even if the sync is then cancelled.
+    async fn publish(
+        conn: &mut PgConnection,
+        staged: &staging::Staged,
+        lock: &SyncLock,
+        run: &mut SyncRun,
+        stats: IngestStats,
+    ) -> Result<(), Error> {
+        staging::check(conn, staged).await?;
+        staging::swap(conn, lock).await?;
 
-        Ok(res.json::<T>().await?)
+        run.finish(Outcome::Success, Some(stats), None);
+        Ok(())
+    }
+
+    /// Streams the elements of a bulk file as the response body arrives.
+    fn get_array<'a, T>(
+        client: &'a Client,
+        bulk: &BulkMetadata,
+    ) -> impl Stream<Item = Result<T, Error>> + 'a
+    where
+        T: DeserializeOwned + 'a,
+    {
+        stream::parse_array(client.bulk(&bulk.download_uri).boxed())
     }
 }
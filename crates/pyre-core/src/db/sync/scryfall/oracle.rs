@@ -0,0 +1,164 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db;
+
+/// A unique card, whatever the number of times it was printed
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct OracleCard {
+    pub oracle_id: Uuid,
+    pub name: String,
+    pub canonical_card_id: Option<Uuid>,
+    pub printings: i64,
+}
+
+/// A printing of a card, as listed next to the other printings
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Printing {
+    pub id: Uuid,
+    /// Missing on reversible cards, whose faces have one each
+    pub oracle_id: Option<Uuid>,
+    pub name: String,
+    pub lang: String,
+    pub released_at: NaiveDate,
+    pub rarity: String,
+    pub set_code: String,
+    pub set_name: String,
+    pub image_normal: Option<String>,
+    pub price_usd: Option<f32>,
+}
+
+/// Selects `Printing` columns, the image is the one of the front face
+pub const PRINTING: &str = r"
+    SELECT
+        c.id, c.oracle_id, c.name, c.lang, c.released_at, c.rarity,
+        s.code AS set_code, s.name AS set_name, f.image_normal, c.price_usd
+    FROM scryfall.cards c
+    JOIN scryfall.sets s ON s.id = c.set_id
+    LEFT JOIN scryfall.card_faces f ON f.card_id = c.id AND f.face_index = 0
+";
+
+impl OracleCard {
+    pub async fn get(dal: sqlx::PgPool, oracle_id: Uuid) -> Result<Option<Self>, db::Error> {
+        Ok(sqlx::query_as(
+            r"
+            SELECT o.oracle_id, o.name, o.canonical_card_id, COUNT(c.id) AS printings
+            FROM scryfall.oracle_cards o
+            LEFT JOIN scryfall.cards c ON c.oracle_id = o.oracle_id
+            WHERE o.oracle_id = $1
+            GROUP BY o.oracle_id
+            ",
+        )
+        .bind(oracle_id)
+        .fetch_optional(&dal)
+        .await?)
+    }
+
+    /// Every printing of the card, most recent first.
+    pub async fn printings(dal: sqlx::PgPool, oracle_id: Uuid) -> Result<Vec<Printing>, db::Error> {
+        Ok(sqlx::query_as(&format!(
+            "{PRINTING} WHERE c.oracle_id = $1 ORDER BY c.released_at DESC, s.code, c.lang"
+        ))
+        .bind(oracle_id)
+        .fetch_all(&dal)
+        .await?)
+    }
+
+    /// Canonical printings of the cards whose name contains `name`, each card once.
+    pub async fn search(
+        dal: sqlx::PgPool,
+        name: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<Printing>, db::Error> {
+        Ok(sqlx::query_as(&format!(
+            r"
+            {PRINTING}
+            JOIN scryfall.oracle_cards o ON o.canonical_card_id = c.id
+            WHERE o.name ILIKE '%' || $1 || '%'
+            ORDER BY o.name
+            LIMIT $2 OFFSET $3
+            "
+        ))
+        .bind(escape_like(name))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&dal)
+        .await?)
+    }
+}
+
+/// Matches `%` and `_` literally in a LIKE pattern.
+pub fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', r"\\")
+        .replace('%', r"\%")
+        .replace('_', r"\_")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{card::Card, ingest, staging, Error},
+        tests::TestDb,
+    };
+
+    async fn printing(oracle_id: Uuid, lang: &str, released_at: &str) -> Result<Card, Error> {
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: Card = serde_json::from_slice(&json)?;
+        card.id = Uuid::new_v4().to_string();
+        card.oracle_id = Some(oracle_id.to_string());
+        card.name = format!("Oracle Elves {oracle_id}");
+        card.lang = lang.to_string();
+        card.released_at = released_at.parse().unwrap();
+        Ok(card)
+    }
+
+    #[tokio::test]
+    async fn test_oracle_cards() {
+        let db = TestDb::new().await;
+        let sql = db.sql.clone();
+        let mut conn = sql.acquire().await.unwrap();
+        let oracle_id = Uuid::new_v4();
+
+        let cards = futures::stream::iter([
+            printing(oracle_id, "en", "2018-04-27").await,
+            printing(oracle_id, "ja", "2024-01-01").await,
+            printing(oracle_id, "en", "2010-01-01").await,
+        ]);
+        ingest::ingest(&mut conn, staging::LIVE, cards)
+            .await
+            .unwrap();
+
+        let oracle = OracleCard::get(sql.clone(), oracle_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(oracle.printings, 3);
+
+        let printings = OracleCard::printings(sql.clone(), oracle_id).await.unwrap();
+        assert_eq!(printings.len(), 3);
+        assert_eq!(printings[0].lang, "ja");
+
+        // The most recent english printing wins over the newer japanese one
+        let canonical = printings
+            .iter()
+            .find(|p| Some(p.id) == oracle.canonical_card_id)
+            .unwrap();
+        assert_eq!(canonical.lang, "en");
+        assert_eq!(canonical.released_at.to_string(), "2018-04-27");
+
+        let found = OracleCard::search(sql.clone(), &oracle_id.to_string(), 10, 0)
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, canonical.id);
+
+        drop(conn);
+        db.close().await;
+    }
+}
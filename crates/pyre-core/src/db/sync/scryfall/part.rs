@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::card::RelatedCard;
+use crate::db;
+
+/// How a related card belongs with a card, as published by Scryfall
+#[derive(
+    sqlx::Type, strum::Display, Copy, Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "part_component", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum Component {
+    /// A token or emblem the card makes
+    Token,
+    /// One of the cards that meld together
+    MeldPart,
+    /// The back face two meld parts make
+    MeldResult,
+    /// Mentions or is mentioned by the card, e.g. Dungeons or the cards of a combo
+    ComboPiece,
+    /// Published after this list was written, such parts are skipped and never stored
+    #[serde(other)]
+    Unknown,
+}
+
+/// A card related to another one, e.g. a token it makes.
+/// The related card is not necessarily synced, its name and type line are kept with it.
+#[derive(sqlx::FromRow, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardPart {
+    pub part_id: Uuid,
+    pub component: Component,
+    pub name: String,
+    pub type_line: String,
+}
+
+impl CardPart {
+    /// Parts of a card in `part_id` order, without the card itself or unknown components.
+    pub fn from_related(card_id: Uuid, related: &[RelatedCard]) -> Result<Vec<Self>, uuid::Error> {
+        let mut parts = related
+            .iter()
+            .filter(|r| {
+                if r.component == Component::Unknown {
+                    warn!(card = %card_id, part = %r.name, "skipping part with an unknown component");
+                }
+                r.component != Component::Unknown
+            })
+            .map(|r| {
+                Ok(Self {
+                    part_id: Uuid::parse_str(&r.id)?,
+                    component: r.component,
+                    name: r.name.clone(),
+                    type_line: r.type_line.clone(),
+                })
+            })
+            .filter(|p| !matches!(p, Ok(p) if p.part_id == card_id))
+            .collect::<Result<Vec<_>, uuid::Error>>()?;
+
+        parts.sort_by_key(|p| p.part_id);
+        parts.dedup_by_key(|p| p.part_id);
+        Ok(parts)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct PartRow {
+    card_id: Uuid,
+    #[sqlx(flatten)]
+    part: CardPart,
+}
+
+/// Loads the parts of cards, keyed by card.
+pub async fn for_cards(
+    conn: &mut PgConnection,
+    ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<CardPart>>, db::Error> {
+    let rows: Vec<PartRow> = sqlx::query_as(
+        r"
+        SELECT card_id, part_id, component, name, type_line
+        FROM scryfall.card_parts
+        WHERE card_id = ANY($1)
+        ORDER BY card_id, part_id
+        ",
+    )
+    .bind(ids)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut parts = HashMap::<Uuid, Vec<CardPart>>::new();
+    for row in rows {
+        parts.entry(row.card_id).or_default().push(row.part);
+    }
+
+    Ok(parts)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{card::Card, db_card::DbCard, ingest, staging},
+        tests::TestDb,
+    };
+
+    #[tokio::test]
+    async fn test_meld_parts() {
+        let json = tokio::fs::read("test/scryfall/meld.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: Card = serde_json::from_slice(&json).unwrap();
+
+        // Bruna lists itself, which is not a part of its own
+        let bruna = Uuid::parse_str(&card.id).unwrap();
+        let related = card.all_parts.as_deref().unwrap();
+        assert_eq!(CardPart::from_related(bruna, related).unwrap().len(), 2);
+
+        // A component Scryfall adds later must not fail the whole card
+        let mut related: Vec<RelatedCard> = serde_json::from_value(serde_json::json!([{
+            "id": "5b4b3bd7-3a2d-4d8a-a8b1-3c7b1b6c8f6e",
+            "component": "attraction",
+            "name": "Balloon Stand",
+            "type_line": "Artifact — Attraction",
+        }]))
+        .unwrap();
+        assert_eq!(related[0].component, Component::Unknown);
+        related.extend(card.all_parts.take().unwrap());
+        assert_eq!(CardPart::from_related(bruna, &related).unwrap().len(), 2);
+        card.all_parts = Some(related);
+
+        let id = Uuid::new_v4();
+        card.id = id.to_string();
+
+        let db = TestDb::new().await;
+        let mut conn = PgConnection::connect(&db.pg).await.unwrap();
+        ingest::ingest(&mut conn, staging::LIVE, futures::stream::iter([Ok(card)]))
+            .await
+            .unwrap();
+
+        let mut cards = DbCard::load(&mut conn, &[id]).await.unwrap();
+        let parts = cards.remove(&id).unwrap().parts.unwrap();
+
+        assert_eq!(parts.len(), 3);
+        let result = parts
+            .iter()
+            .find(|p| p.component == Component::MeldResult)
+            .unwrap();
+        assert_eq!(result.name, "Brisela, Voice of Nightmares");
+        assert_eq!(
+            parts
+                .iter()
+                .filter(|p| p.component == Component::MeldPart)
+                .count(),
+            2
+        );
+
+        conn.close().await.unwrap();
+        db.close().await;
+    }
+}
@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::card::Prices;
+use crate::db;
+
+#[derive(sqlx::Type, strum::Display, Copy, Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[sqlx(type_name = "price_finish", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Finish {
+    Nonfoil,
+    Foil,
+    Etched,
+}
+
+#[derive(
+    sqlx::Type, strum::Display, Copy, Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "price_currency", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Currency {
+    #[default]
+    Usd,
+    Eur,
+    Tix,
+}
+
+/// A price of a card as observed by a sync, `None` once Scryfall stops listing it
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct CardPrice {
+    pub card_id: Uuid,
+    pub finish: Finish,
+    pub currency: Currency,
+    pub price: Option<f32>,
+    pub observed_at: DateTime<Utc>,
+}
+
+impl CardPrice {
+    /// Every price of a card, `observed_at` is set by the database on insert.
+    pub fn from_prices(card_id: Uuid, prices: &Prices) -> Vec<Self> {
+        [
+            (Finish::Nonfoil, Currency::Usd, &prices.usd),
+            (Finish::Foil, Currency::Usd, &prices.usd_foil),
+            (Finish::Etched, Currency::Usd, &prices.usd_etched),
+            (Finish::Nonfoil, Currency::Eur, &prices.eur),
+            (Finish::Foil, Currency::Eur, &prices.eur_foil),
+            (Finish::Nonfoil, Currency::Tix, &prices.tix),
+        ]
+        .into_iter()
+        .map(|(finish, currency, price)| {
+            Self {
+                card_id,
+                finish,
+                currency,
+                price: parse_price(price.as_deref()),
+                observed_at: DateTime::default(),
+            }
+        })
+        .collect()
+    }
+
+    /// Price changes of a card in a currency within `[from, to]`, oldest first.
+    pub async fn series(
+        dal: sqlx::PgPool,
+        card_id: Uuid,
+        currency: Currency,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Self>, db::Error> {
+        Ok(sqlx::query_as(
+            r"
+            SELECT card_id, finish, currency, price, observed_at
+            FROM card_prices
+            WHERE card_id = $1 AND currency = $2 AND observed_at BETWEEN $3 AND $4
+            ORDER BY observed_at, finish
+            ",
+        )
+        .bind(card_id)
+        .bind(currency)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&dal)
+        .await?)
+    }
+}
+
+pub fn parse_price(price: Option<&str>) -> Option<f32> {
+    price.and_then(|p| p.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{card::Card, ingest, staging, Error},
+        tests::TestDb,
+    };
+
+    async fn card(id: Uuid, usd: &str) -> Result<Card, Error> {
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: Card = serde_json::from_slice(&json)?;
+        card.id = id.to_string();
+        card.prices.usd = Some(usd.to_string());
+        Ok(card)
+    }
+
+    #[tokio::test]
+    async fn test_price_history() {
+        let db = TestDb::new().await;
+        let sql = db.sql.clone();
+        let mut conn = sql.acquire().await.unwrap();
+        let id = Uuid::new_v4();
+        let from = Utc::now();
+
+        // The second sync has the same prices, only the third one changes a price
+        for usd in ["0.28", "0.28", "0.35"] {
+            let cards = futures::stream::iter([card(id, usd).await]);
+            ingest::ingest(&mut conn, staging::LIVE, cards)
+                .await
+                .unwrap();
+        }
+
+        let to = Utc::now() + TimeDelta::seconds(1);
+        let series = CardPrice::series(sql.clone(), id, Currency::Usd, from, to)
+            .await
+            .unwrap();
+
+        let nonfoil = series
+            .iter()
+            .filter(|p| p.finish == Finish::Nonfoil)
+            .map(|p| p.price)
+            .collect::<Vec<_>>();
+        assert_eq!(nonfoil, vec![Some(0.28), Some(0.35)]);
+
+        // Etched has never been priced, so it has no history
+        assert!(series.iter().all(|p| p.finish != Finish::Etched));
+        assert_eq!(
+            series.iter().filter(|p| p.finish == Finish::Foil).count(),
+            1
+        );
+
+        drop(conn);
+        db.close().await;
+    }
+}
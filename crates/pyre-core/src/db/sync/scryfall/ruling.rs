@@ -0,0 +1,133 @@
+use chrono::NaiveDate;
+use futures::{pin_mut, Stream, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::{ingest::BATCH_SIZE, Error};
+use crate::db;
+
+/// An official (wotc) or Scryfall note on how a card works
+#[derive(sqlx::FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct Ruling {
+    pub oracle_id: Uuid,
+    /// "wotc" or "scryfall"
+    pub source: String,
+    pub published_at: NaiveDate,
+    pub comment: String,
+}
+
+impl Ruling {
+    /// Rulings of a card, oldest first.
+    pub async fn for_oracle(dal: sqlx::PgPool, oracle_id: Uuid) -> Result<Vec<Self>, db::Error> {
+        Ok(sqlx::query_as(
+            r"
+            SELECT oracle_id, source, published_at, comment
+            FROM scryfall.rulings
+            WHERE oracle_id = $1
+            ORDER BY published_at, id
+            ",
+        )
+        .bind(oracle_id)
+        .fetch_all(&dal)
+        .await?)
+    }
+}
+
+/// Replaces all rulings with the ones of the stream in a single transaction.
+/// Rulings have no identifier of their own, so they are not upserted.
+pub async fn ingest<S>(conn: &mut PgConnection, schema: &str, rulings: S) -> Result<u64, Error>
+where
+    S: Stream<Item = Result<Ruling, Error>>,
+{
+    pin_mut!(rulings);
+
+    let mut tx = sqlx::Connection::begin(&mut *conn).await?;
+    sqlx::query(&format!("SET LOCAL search_path TO {schema}, public"))
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM rulings").execute(&mut *tx).await?;
+
+    let mut count = 0;
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    loop {
+        let ruling = rulings.try_next().await?;
+        let done = ruling.is_none();
+        batch.extend(ruling);
+
+        if batch.len() >= BATCH_SIZE || (done && !batch.is_empty()) {
+            count += insert(&mut tx, &batch).await?;
+            batch.clear();
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok(count)
+}
+
+async fn insert(conn: &mut PgConnection, rulings: &[Ruling]) -> Result<u64, Error> {
+    let res = sqlx::query(
+        r"
+        INSERT INTO rulings (oracle_id, source, published_at, comment)
+        SELECT * FROM UNNEST($1::uuid[], $2::text[], $3::date[], $4::text[])
+        ",
+    )
+    .bind(rulings.iter().map(|r| r.oracle_id).collect::<Vec<_>>())
+    .bind(
+        rulings
+            .iter()
+            .map(|r| r.source.as_str())
+            .collect::<Vec<_>>(),
+    )
+    .bind(rulings.iter().map(|r| r.published_at).collect::<Vec<_>>())
+    .bind(
+        rulings
+            .iter()
+            .map(|r| r.comment.as_str())
+            .collect::<Vec<_>>(),
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(res.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{staging, stream},
+        tests::TestDb,
+    };
+
+    #[tokio::test]
+    async fn test_rulings_ingest() {
+        let json = tokio::fs::read("test/scryfall/rulings.json")
+            .await
+            .expect("Failed to read file");
+
+        // The ingest replaces every ruling
+        let db = TestDb::new().await;
+        let mut conn = db.sql.acquire().await.unwrap();
+
+        let body = futures::stream::iter([Ok::<_, Error>(json)]);
+        let count = ingest(&mut conn, staging::LIVE, stream::parse_array(body))
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+
+        let oracle_id = Uuid::parse_str("68954295-54e3-4303-a6bc-fc4547a4e3a3").unwrap();
+        let rulings = Ruling::for_oracle(db.sql.clone(), oracle_id).await.unwrap();
+        assert_eq!(rulings.len(), 2);
+        assert!(rulings[0].published_at <= rulings[1].published_at);
+        assert_eq!(rulings[0].source, "wotc");
+
+        drop(conn);
+        db.close().await;
+    }
+}
@@ -0,0 +1,217 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{bulk::BulkMetadata, ingest::IngestStats};
+use crate::db::{self, Dao};
+
+/// Channel notified with the run id once a sync put new data live
+pub const SYNCED: &str = "scryfall_synced";
+
+#[derive(
+    sqlx::Type, strum::Display, Copy, Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize,
+)]
+#[sqlx(type_name = "sync_outcome", rename_all = "lowercase")]
+pub enum Outcome {
+    #[default]
+    Running,
+    Success,
+    Skipped,
+    Failed,
+    Cancelled,
+    /// Put the previous data back live, see [`super::staging::rollback`]
+    Rollback,
+}
+
+/// A single dbsync run, recorded whether or not it ingested anything
+#[derive(sqlx::FromRow, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SyncRun {
+    pub id: i32,
+
+    pub bulk_id: Option<Uuid>,
+    pub bulk_updated_at: Option<DateTime<Utc>>,
+
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+
+    pub cards: i64,
+    pub faces: i64,
+    pub sets: i64,
+    pub rulings: i64,
+
+    pub outcome: Outcome,
+    pub error: Option<String>,
+}
+
+impl SyncRun {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_bulk(&mut self, bulk: &BulkMetadata) -> Result<(), uuid::Error> {
+        self.bulk_id = Some(Uuid::parse_str(&bulk.id)?);
+        self.bulk_updated_at = Some(bulk.updated_at);
+        Ok(())
+    }
+
+    pub fn finish(&mut self, outcome: Outcome, stats: Option<IngestStats>, error: Option<String>) {
+        let stats = stats.unwrap_or_default();
+
+        self.finished_at = Some(Utc::now());
+        self.outcome = outcome;
+        self.cards = i64::try_from(stats.cards).unwrap_or(i64::MAX);
+        self.faces = i64::try_from(stats.faces).unwrap_or(i64::MAX);
+        self.sets = i64::try_from(stats.sets).unwrap_or(i64::MAX);
+        self.rulings = i64::try_from(stats.rulings).unwrap_or(i64::MAX);
+        self.error = error;
+    }
+
+    /// The bulk `updated_at` of the last successful run, if any.
+    pub async fn last_success(dal: sqlx::PgPool) -> Result<Option<DateTime<Utc>>, db::Error> {
+        Ok(sqlx::query_scalar(
+            r"
+            SELECT MAX(bulk_updated_at)
+            FROM sync_runs
+            WHERE outcome = 'success'
+            ",
+        )
+        .fetch_one(&dal)
+        .await?)
+    }
+
+    /// The id of the last run that put data live, the data version served by the API.
+    pub async fn live_version(dal: sqlx::PgPool) -> Result<Option<i32>, db::Error> {
+        Ok(sqlx::query_scalar(
+            r"
+            SELECT MAX(id)
+            FROM sync_runs
+            WHERE outcome IN ('success', 'rollback')
+            ",
+        )
+        .fetch_one(&dal)
+        .await?)
+    }
+
+    /// Tells the servers listening on [`SYNCED`] that `version` is live.
+    pub async fn notify(dal: sqlx::PgPool, version: i32) -> Result<(), db::Error> {
+        sqlx::query("SELECT pg_notify($1, $2)")
+            .bind(SYNCED)
+            .bind(version.to_string())
+            .execute(&dal)
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl Dao for SyncRun {
+    type Id = i32;
+
+    type Dal = sqlx::PgPool;
+
+    async fn get(dal: Self::Dal, id: Self::Id) -> Result<Option<Self>, db::Error> {
+        Ok(sqlx::query_as("SELECT * FROM sync_runs WHERE id = $1")
+            .bind(id)
+            .fetch_optional(&dal)
+            .await?)
+    }
+
+    async fn delete(dal: Self::Dal, id: Self::Id) -> Result<(), db::Error> {
+        sqlx::query("DELETE FROM sync_runs WHERE id = $1")
+            .bind(id)
+            .execute(&dal)
+            .await?;
+        Ok(())
+    }
+
+    async fn create(&mut self, dal: Self::Dal) -> Result<(), db::Error> {
+        self.id = sqlx::query_scalar(
+            r"
+            INSERT INTO sync_runs (started_at, outcome)
+            VALUES ($1, $2)
+            RETURNING id
+            ",
+        )
+        .bind(self.started_at)
+        .bind(self.outcome)
+        .fetch_one(&dal)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update(&self, dal: Self::Dal) -> Result<Self::Id, db::Error> {
+        sqlx::query(
+            r"
+            UPDATE sync_runs
+            SET bulk_id = $2,
+                bulk_updated_at = $3,
+                finished_at = $4,
+                cards = $5,
+                faces = $6,
+                sets = $7,
+                rulings = $8,
+                outcome = $9,
+                error = $10
+            WHERE id = $1
+            ",
+        )
+        .bind(self.id)
+        .bind(self.bulk_id)
+        .bind(self.bulk_updated_at)
+        .bind(self.finished_at)
+        .bind(self.cards)
+        .bind(self.faces)
+        .bind(self.sets)
+        .bind(self.rulings)
+        .bind(self.outcome)
+        .bind(&self.error)
+        .execute(&dal)
+        .await?;
+
+        Ok(self.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::svc::state::DbConfig;
+
+    #[tokio::test]
+    async fn test_sync_run() {
+        let sql = sqlx::PgPool::connect(&DbConfig::default().pg)
+            .await
+            .unwrap();
+
+        let mut run = SyncRun::new();
+        run.create(sql.clone()).await.unwrap();
+        assert!(run.id > 0);
+
+        run.bulk_id = Some(Uuid::new_v4());
+        run.bulk_updated_at = Some(Utc::now());
+        run.finish(
+            Outcome::Skipped,
+            Some(IngestStats {
+                cards: 3,
+                faces: 4,
+                sets: 1,
+                rulings: 2,
+                unknown_symbols: 0,
+            }),
+            None,
+        );
+        run.update(sql.clone()).await.unwrap();
+
+        let stored = SyncRun::get(sql.clone(), run.id).await.unwrap().unwrap();
+        assert_eq!(stored.outcome, Outcome::Skipped);
+        assert_eq!(stored.faces, 4);
+        assert_eq!(stored.rulings, 2);
+        assert!(stored.finished_at.is_some());
+
+        SyncRun::delete(sql, run.id).await.unwrap();
+    }
+}
@@ -0,0 +1,154 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use super::{db_card::DbSet, Error};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetList {
+    pub data: Vec<Set>,
+}
+
+#[allow(clippy::struct_field_names)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Set {
+    pub id: Uuid,
+    pub code: String,
+    pub name: String,
+    pub set_type: String,
+    pub scryfall_uri: String,
+    /// Unknown for a few unreleased sets
+    pub released_at: Option<NaiveDate>,
+    pub card_count: i32,
+    /// Tokens, promos and other child sets, e.g. "tdom" has "dom"
+    pub parent_set_code: Option<String>,
+    pub block_code: Option<String>,
+    pub block: Option<String>,
+    pub digital: bool,
+    pub icon_svg_uri: String,
+}
+
+impl From<Set> for DbSet {
+    fn from(set: Set) -> Self {
+        Self {
+            id: set.id,
+            code: set.code,
+            name: set.name,
+            set_type: set.set_type,
+            card_count: set.card_count,
+            scryfall_uri: set.scryfall_uri,
+            released_at: set.released_at,
+            parent_set_code: set.parent_set_code,
+            block_code: set.block_code,
+            block: set.block,
+            digital: set.digital,
+            icon_svg_uri: Some(set.icon_svg_uri),
+        }
+    }
+}
+
+/// Upserts every set, returns the number of rows written.
+/// Runs before the card ingest so that cards always find their set.
+pub async fn upsert(conn: &mut PgConnection, schema: &str, sets: SetList) -> Result<u64, Error> {
+    let sets = sets.data.into_iter().map(DbSet::from).collect::<Vec<_>>();
+
+    let res = sqlx::query(&format!(
+        r"
+        INSERT INTO {schema}.sets (
+            id, code, name, set_type, scryfall_uri, released_at, card_count,
+            parent_set_code, block_code, block, digital, icon_svg_uri
+        )
+        SELECT * FROM UNNEST(
+            $1::uuid[], $2::text[], $3::text[], $4::text[], $5::text[], $6::date[], $7::int[],
+            $8::text[], $9::text[], $10::text[], $11::bool[], $12::text[]
+        )
+        ON CONFLICT (id) DO UPDATE SET
+            code = EXCLUDED.code,
+            name = EXCLUDED.name,
+            set_type = EXCLUDED.set_type,
+            scryfall_uri = EXCLUDED.scryfall_uri,
+            released_at = EXCLUDED.released_at,
+            card_count = EXCLUDED.card_count,
+            parent_set_code = EXCLUDED.parent_set_code,
+            block_code = EXCLUDED.block_code,
+            block = EXCLUDED.block,
+            digital = EXCLUDED.digital,
+            icon_svg_uri = EXCLUDED.icon_svg_uri
+        "
+    ))
+    .bind(sets.iter().map(|s| s.id).collect::<Vec<_>>())
+    .bind(sets.iter().map(|s| s.code.as_str()).collect::<Vec<_>>())
+    .bind(sets.iter().map(|s| s.name.as_str()).collect::<Vec<_>>())
+    .bind(sets.iter().map(|s| s.set_type.as_str()).collect::<Vec<_>>())
+    .bind(
+        sets.iter()
+            .map(|s| s.scryfall_uri.as_str())
+            .collect::<Vec<_>>(),
+    )
+    .bind(sets.iter().map(|s| s.released_at).collect::<Vec<_>>())
+    .bind(sets.iter().map(|s| s.card_count).collect::<Vec<_>>())
+    .bind(
+        sets.iter()
+            .map(|s| s.parent_set_code.as_deref())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        sets.iter()
+            .map(|s| s.block_code.as_deref())
+            .collect::<Vec<_>>(),
+    )
+    .bind(sets.iter().map(|s| s.block.as_deref()).collect::<Vec<_>>())
+    .bind(sets.iter().map(|s| s.digital).collect::<Vec<_>>())
+    .bind(
+        sets.iter()
+            .map(|s| s.icon_svg_uri.as_deref())
+            .collect::<Vec<_>>(),
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(res.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+
+    use super::*;
+    use crate::db::{sync::scryfall::staging, tests::TestDb};
+
+    #[tokio::test]
+    async fn test_set_upsert() {
+        let json = tokio::fs::read("test/scryfall/sets.json")
+            .await
+            .expect("Failed to read file");
+        let sets: SetList = serde_json::from_slice(&json).unwrap();
+        assert_eq!(sets.data.len(), 4);
+
+        let db = TestDb::new().await;
+        let mut conn = PgConnection::connect(&db.pg).await.unwrap();
+
+        assert_eq!(upsert(&mut conn, staging::LIVE, sets).await.unwrap(), 4);
+
+        let (parent, card_count, digital): (Option<String>, i32, bool) = sqlx::query_as(
+            "SELECT parent_set_code, card_count, digital FROM scryfall.sets WHERE code = 'tdom'",
+        )
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(parent.as_deref(), Some("dom"));
+        assert_eq!(card_count, 14);
+        assert!(!digital);
+
+        let block: Option<String> =
+            sqlx::query_scalar("SELECT block FROM scryfall.sets WHERE code = 'tmp'")
+                .fetch_one(&mut conn)
+                .await
+                .unwrap();
+        assert_eq!(block.as_deref(), Some("Tempest"));
+
+        conn.close().await.unwrap();
+        db.close().await;
+    }
+}
@@ -0,0 +1,431 @@
+use sqlx::{Connection, PgConnection};
+use tracing::info;
+
+use super::{lock::SyncLock, Error};
+
+/// Schema the server reads from
+pub const LIVE: &str = "scryfall";
+
+/// Schema a sync writes to, swapped in once it passes the integrity checks
+pub const NEXT: &str = "scryfall_next";
+
+/// The live schema before the last swap, kept for rollback
+pub const PREVIOUS: &str = "scryfall_prev";
+
+/// Tables a sync only ever adds to, fewer rows after the ingest means data was lost
+const GROWING: &[&str] = &["cards", "sets", "oracle_cards"];
+
+/// Longest wait for the locks of a swap, readers are never blocked longer than this
+const LOCK_TIMEOUT: &str = "10s";
+
+/// A serial column and the sequence behind it
+#[derive(sqlx::FromRow)]
+struct Serial {
+    table_name: String,
+    column_name: String,
+    sequence_name: String,
+    data_type: String,
+}
+
+/// Row counts of the [`GROWING`] tables when the staging schema was prepared
+#[derive(Debug, Clone, Default)]
+pub struct Staged {
+    counts: Vec<(&'static str, i64)>,
+}
+
+/// A primary key, unique or foreign key constraint
+#[derive(sqlx::FromRow)]
+struct Constraint {
+    table_name: String,
+    name: String,
+    definition: String,
+}
+
+/// Copies the live schema with its data into [`NEXT`], replacing any previous staging.
+///
+/// Tables, defaults, sequences, constraints and indexes are recreated under their own names.
+/// History such as prices and legality changes lives in public and is not copied.
+pub async fn prepare(conn: &mut PgConnection) -> Result<Staged, Error> {
+    let mut tx = conn.begin().await?;
+    // Keeps catalog definitions schema qualified
+    sqlx::query("SET LOCAL search_path TO public")
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(&format!("DROP SCHEMA IF EXISTS {NEXT} CASCADE"))
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query(&format!("CREATE SCHEMA {NEXT}"))
+        .execute(&mut *tx)
+        .await?;
+
+    // Data goes in before constraints and indexes, which also sidesteps the cycle
+    // between cards and oracle cards
+    let tables = copy_tables(&mut tx).await?;
+    copy_sequences(&mut tx).await?;
+    copy_constraints(&mut tx).await?;
+    let indexes = copy_indexes(&mut tx).await?;
+
+    tx.commit().await?;
+
+    let mut staged = Staged::default();
+    for table in GROWING {
+        staged.counts.push((table, count(conn, table).await?));
+    }
+
+    info!(tables, indexes, "prepared staging schema");
+    Ok(staged)
+}
+
+async fn copy_tables(conn: &mut PgConnection) -> Result<usize, Error> {
+    let tables: Vec<String> = sqlx::query_scalar(
+        "SELECT tablename::text FROM pg_tables WHERE schemaname = $1 ORDER BY tablename",
+    )
+    .bind(LIVE)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for table in &tables {
+        sqlx::query(&format!(
+            r"
+            CREATE TABLE {NEXT}.{table} (
+                LIKE {LIVE}.{table}
+                INCLUDING DEFAULTS INCLUDING CONSTRAINTS INCLUDING GENERATED
+                INCLUDING IDENTITY INCLUDING STORAGE INCLUDING COMMENTS
+            )
+            "
+        ))
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query(&format!(
+            "INSERT INTO {NEXT}.{table} SELECT * FROM {LIVE}.{table}"
+        ))
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(tables.len())
+}
+
+/// Copied defaults still point at the live sequences, each gets its own at the same value.
+async fn copy_sequences(conn: &mut PgConnection) -> Result<(), Error> {
+    let serials: Vec<Serial> = sqlx::query_as(
+        r"
+        SELECT t.relname::text AS table_name, a.attname::text AS column_name,
+            s.relname::text AS sequence_name, format_type(ps.seqtypid, NULL) AS data_type
+        FROM pg_class s
+        JOIN pg_sequence ps ON ps.seqrelid = s.oid
+        JOIN pg_depend d ON d.objid = s.oid AND d.deptype = 'a'
+        JOIN pg_class t ON t.oid = d.refobjid
+        JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = d.refobjsubid
+        WHERE s.relkind = 'S' AND s.relnamespace = $1::regnamespace
+        ",
+    )
+    .bind(LIVE)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for s in &serials {
+        let (table, column, sequence) = (&s.table_name, &s.column_name, &s.sequence_name);
+        sqlx::query(&format!(
+            "CREATE SEQUENCE {NEXT}.{sequence} AS {} OWNED BY {NEXT}.{table}.{column}",
+            s.data_type
+        ))
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query(&format!(
+            "ALTER TABLE {NEXT}.{table} ALTER COLUMN {column} SET DEFAULT nextval('{NEXT}.{sequence}')"
+        ))
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query(&format!(
+            "SELECT setval('{NEXT}.{sequence}', last_value, is_called) FROM {LIVE}.{sequence}"
+        ))
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Keys first so that foreign keys find them, references are moved to the staged tables.
+async fn copy_constraints(conn: &mut PgConnection) -> Result<(), Error> {
+    let constraints: Vec<Constraint> = sqlx::query_as(
+        r"
+        SELECT t.relname::text AS table_name, c.conname::text AS name,
+            pg_get_constraintdef(c.oid) AS definition
+        FROM pg_constraint c
+        JOIN pg_class t ON t.oid = c.conrelid
+        WHERE c.connamespace = $1::regnamespace AND c.contype IN ('p', 'u', 'x', 'f')
+        ORDER BY c.contype = 'f', t.relname, c.conname
+        ",
+    )
+    .bind(LIVE)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for c in &constraints {
+        let definition = c.definition.replace(
+            &format!("REFERENCES {LIVE}."),
+            &format!("REFERENCES {NEXT}."),
+        );
+        sqlx::query(&format!(
+            "ALTER TABLE {NEXT}.{} ADD CONSTRAINT {} {definition}",
+            c.table_name, c.name
+        ))
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Indexes not backing a constraint, under the same names so migrations still find them.
+async fn copy_indexes(conn: &mut PgConnection) -> Result<usize, Error> {
+    let indexes: Vec<String> = sqlx::query_scalar(
+        r"
+        SELECT indexdef FROM pg_indexes
+        WHERE schemaname = $1
+            AND indexname NOT IN (
+                SELECT conname FROM pg_constraint
+                WHERE connamespace = $1::regnamespace AND contype IN ('p', 'u', 'x')
+            )
+        ",
+    )
+    .bind(LIVE)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for index in &indexes {
+        sqlx::query(&index.replacen(&format!(" ON {LIVE}."), &format!(" ON {NEXT}."), 1))
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    Ok(indexes.len())
+}
+
+/// Checks the staged data before it goes live: no table a sync only adds to has lost rows,
+/// every reference resolves and set codes are unique.
+pub async fn check(conn: &mut PgConnection, staged: &Staged) -> Result<(), Error> {
+    for (table, before) in &staged.counts {
+        let after = count(conn, table).await?;
+        if after < *before {
+            return Err(Error::Integrity(format!(
+                "{table} went from {before} to {after} rows"
+            )));
+        }
+    }
+
+    let unresolved: Vec<(String, i64)> = sqlx::query_as(&format!(
+        r"
+        SELECT 'cards.set_id', COUNT(*) FROM {NEXT}.cards c
+        WHERE NOT EXISTS (SELECT 1 FROM {NEXT}.sets s WHERE s.id = c.set_id)
+        UNION ALL
+        SELECT 'cards.layout_id', COUNT(*) FROM {NEXT}.cards c
+        WHERE NOT EXISTS (SELECT 1 FROM {NEXT}.layouts l WHERE l.id = c.layout_id)
+        UNION ALL
+        SELECT 'cards.oracle_id', COUNT(*) FROM {NEXT}.cards c
+        WHERE c.oracle_id IS NOT NULL
+            AND NOT EXISTS (SELECT 1 FROM {NEXT}.oracle_cards o WHERE o.oracle_id = c.oracle_id)
+        UNION ALL
+        SELECT 'card_faces.card_id', COUNT(*) FROM {NEXT}.card_faces f
+        WHERE NOT EXISTS (SELECT 1 FROM {NEXT}.cards c WHERE c.id = f.card_id)
+        UNION ALL
+        SELECT 'oracle_cards.canonical_card_id', COUNT(*) FROM {NEXT}.oracle_cards o
+        WHERE o.canonical_card_id IS NOT NULL
+            AND NOT EXISTS (SELECT 1 FROM {NEXT}.cards c WHERE c.id = o.canonical_card_id)
+        "
+    ))
+    .fetch_all(&mut *conn)
+    .await?;
+
+    if let Some((reference, count)) = unresolved.into_iter().find(|(_, count)| *count > 0) {
+        return Err(Error::Integrity(format!(
+            "{count} rows with an unresolved {reference}"
+        )));
+    }
+
+    let duplicate: Option<String> = sqlx::query_scalar(&format!(
+        "SELECT code FROM {NEXT}.sets GROUP BY code HAVING COUNT(*) > 1 LIMIT 1"
+    ))
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(code) = duplicate {
+        return Err(Error::Integrity(format!("duplicate set code {code}")));
+    }
+
+    Ok(())
+}
+
+/// Puts the staged schema live in a single transaction, the live one becomes [`PREVIOUS`].
+///
+/// Queries resolve schema names when planned, so readers see either version in full.
+/// Nothing is swapped unless `lock` is still held when the transaction runs.
+pub async fn swap(conn: &mut PgConnection, lock: &SyncLock) -> Result<(), Error> {
+    let tables: Vec<String> =
+        sqlx::query_scalar("SELECT tablename::text FROM pg_tables WHERE schemaname = $1")
+            .bind(NEXT)
+            .fetch_all(&mut *conn)
+            .await?;
+
+    // Fresh tables have no planner statistics until autovacuum gets to them
+    for table in &tables {
+        sqlx::query(&format!("ANALYZE {NEXT}.{table}"))
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let mut tx = conn.begin().await?;
+    sqlx::query(&format!("SET LOCAL lock_timeout = '{LOCK_TIMEOUT}'"))
+        .execute(&mut *tx)
+        .await?;
+
+    // The ingest may take long, another dbsync may hold the lock by now
+    lock.verify(&mut tx).await?;
+
+    for statement in [
+        format!("DROP SCHEMA IF EXISTS {PREVIOUS} CASCADE"),
+        format!("ALTER SCHEMA {LIVE} RENAME TO {PREVIOUS}"),
+        format!("ALTER SCHEMA {NEXT} RENAME TO {LIVE}"),
+    ] {
+        sqlx::query(&statement).execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+
+    info!("swapped staging schema live");
+    Ok(())
+}
+
+/// Puts [`PREVIOUS`] back live, the live schema takes its place so a rollback can be undone.
+///
+/// Migrations applied after the swap are not in the previous schema.
+pub async fn rollback(conn: &mut PgConnection) -> Result<(), Error> {
+    let mut tx = conn.begin().await?;
+
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM pg_namespace WHERE nspname = $1)")
+            .bind(PREVIOUS)
+            .fetch_one(&mut *tx)
+            .await?;
+
+    if !exists {
+        return Err(Error::NoPrevious);
+    }
+
+    sqlx::query(&format!("SET LOCAL lock_timeout = '{LOCK_TIMEOUT}'"))
+        .execute(&mut *tx)
+        .await?;
+
+    for statement in [
+        format!("DROP SCHEMA IF EXISTS {NEXT} CASCADE"),
+        format!("ALTER SCHEMA {LIVE} RENAME TO {NEXT}"),
+        format!("ALTER SCHEMA {PREVIOUS} RENAME TO {LIVE}"),
+        format!("ALTER SCHEMA {NEXT} RENAME TO {PREVIOUS}"),
+    ] {
+        sqlx::query(&statement).execute(&mut *tx).await?;
+    }
+
+    tx.commit().await?;
+
+    info!("rolled back to the previous scryfall schema");
+    Ok(())
+}
+
+async fn count(conn: &mut PgConnection, table: &str) -> Result<i64, Error> {
+    Ok(
+        sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {NEXT}.{table}"))
+            .fetch_one(&mut *conn)
+            .await?,
+    )
+}
+
+/// Drops the staged schema of a failed or cancelled sync.
+pub async fn discard(conn: &mut PgConnection) -> Result<(), Error> {
+    sqlx::query(&format!("DROP SCHEMA IF EXISTS {NEXT} CASCADE"))
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::svc::state::DbConfig;
+
+    /// Held by tests running dbsync, its staging schema and lock are shared
+    pub static SYNC: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    #[tokio::test]
+    async fn test_prepare_and_check() {
+        let _sync = SYNC.lock().await;
+        let mut conn = PgConnection::connect(&DbConfig::default().pg)
+            .await
+            .unwrap();
+
+        let set_id = Uuid::new_v4();
+        sqlx::query(
+            r"
+            INSERT INTO scryfall.sets (id, code, name, set_type, scryfall_uri)
+            VALUES ($1, $2, 'Staging', 'memorabilia', 'https://scryfall.com/sets/staging')
+            ",
+        )
+        .bind(set_id)
+        .bind(&set_id.simple().to_string()[..8])
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let staged = prepare(&mut conn).await.unwrap();
+        check(&mut conn, &staged).await.unwrap();
+
+        // Foreign keys and sequences belong to the staging schema
+        let referenced: String = sqlx::query_scalar(
+            r"
+            SELECT n.nspname::text FROM pg_constraint c
+            JOIN pg_class t ON t.oid = c.confrelid
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            WHERE c.conname = 'cards_set_id_fkey' AND c.connamespace = $1::regnamespace
+            ",
+        )
+        .bind(NEXT)
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+        assert_eq!(referenced, NEXT);
+
+        let default: String = sqlx::query_scalar(
+            r"
+            SELECT column_default FROM information_schema.columns
+            WHERE table_schema = $1 AND table_name = 'layouts' AND column_name = 'id'
+            ",
+        )
+        .bind(NEXT)
+        .fetch_one(&mut conn)
+        .await
+        .unwrap();
+        assert!(default.contains(NEXT), "{default}");
+
+        // A set missing from the staged data fails the row counts
+        sqlx::query(&format!("DELETE FROM {NEXT}.sets WHERE id = $1"))
+            .bind(set_id)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        assert!(matches!(
+            check(&mut conn, &staged).await,
+            Err(Error::Integrity(_))
+        ));
+
+        discard(&mut conn).await.unwrap();
+        sqlx::query("DELETE FROM scryfall.sets WHERE id = $1")
+            .bind(set_id)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    encode::IsNull,
+    error::BoxDynError,
+    postgres::{PgArgumentBuffer, PgTypeInfo, PgValueRef},
+    Decode,
+    Encode,
+    Postgres,
+};
+
+/// Characters that make a stat depend on the game state, e.g. `*`, `1+*`, `X` or `*²`
+const VARIABLE: &[char] = &['*', 'X', 'Y', '?', '²'];
+
+/// Power, toughness or loyalty as printed, with the number it compares as.
+///
+/// Stored as text with the value in a numeric column next to it,
+/// so range queries work and values such as `1+*` or `∞` are never lost.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StatValue {
+    /// As printed, e.g. `3`, `*`, `1+*`, `X`, `?`, `½` or `∞`
+    pub text: String,
+    /// The fixed part, variable parts count as zero, `None` when not a number such as `∞`
+    pub value: Option<f32>,
+    /// Depends on the game state
+    pub variable: bool,
+}
+
+impl StatValue {
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let variable = text.contains(VARIABLE);
+
+        // The fixed part comes first, as in `1+*` or `7-*`
+        let base = text
+            .split(VARIABLE)
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(['+', '-'])
+            .trim();
+
+        let value = match base {
+            "" if variable => Some(0.0),
+            base => parse_number(base),
+        };
+
+        Self {
+            text: text.to_string(),
+            value,
+            variable,
+        }
+    }
+}
+
+impl std::fmt::Display for StatValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+/// A whole, signed or decimal number, with `½` for the halves of Un-sets.
+fn parse_number(text: &str) -> Option<f32> {
+    match text.strip_suffix('½') {
+        Some("") => Some(0.5),
+        Some(whole) => whole.parse::<f32>().ok().map(|n| n + 0.5f32.copysign(n)),
+        None => text.parse::<f32>().ok().filter(|n| n.is_finite()),
+    }
+}
+
+/// Stored as its text, the value is parsed back on decode
+impl sqlx::Type<Postgres> for StatValue {
+    fn type_info() -> PgTypeInfo {
+        <String as sqlx::Type<Postgres>>::type_info()
+    }
+
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <String as sqlx::Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl Encode<'_, Postgres> for StatValue {
+    fn encode_by_ref(&self, buf: &mut PgArgumentBuffer) -> Result<IsNull, BoxDynError> {
+        <&str as Encode<Postgres>>::encode(self.text.as_str(), buf)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for StatValue {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(Self::parse(<&str as Decode<Postgres>>::decode(value)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::db::{
+        sync::scryfall::{card::Card, db_card::DbCard, ingest, staging},
+        tests::TestDb,
+    };
+
+    fn parsed(text: &str) -> (Option<f32>, bool) {
+        let stat = StatValue::parse(text);
+        assert_eq!(stat.text, text);
+        (stat.value, stat.variable)
+    }
+
+    #[test]
+    fn test_stat_value() {
+        assert_eq!(parsed("3"), (Some(3.0), false));
+        assert_eq!(parsed("0"), (Some(0.0), false));
+        assert_eq!(parsed("-1"), (Some(-1.0), false));
+        assert_eq!(parsed("+2"), (Some(2.0), false));
+        assert_eq!(parsed("*"), (Some(0.0), true));
+        assert_eq!(parsed("1+*"), (Some(1.0), true));
+        assert_eq!(parsed("7-*"), (Some(7.0), true));
+        assert_eq!(parsed("*²"), (Some(0.0), true));
+        assert_eq!(parsed("X"), (Some(0.0), true));
+        assert_eq!(parsed("?"), (Some(0.0), true));
+        assert_eq!(parsed("½"), (Some(0.5), false));
+        assert_eq!(parsed("3½"), (Some(3.5), false));
+        assert_eq!(parsed("3.5"), (Some(3.5), false));
+        assert_eq!(parsed("∞"), (None, false));
+        assert_eq!(parsed("inf"), (None, false));
+    }
+
+    #[tokio::test]
+    async fn test_stat_value_roundtrip() {
+        let db = TestDb::new().await;
+        let sql = db.sql.clone();
+        let mut conn = sql.acquire().await.unwrap();
+        let id = Uuid::new_v4();
+
+        let json = tokio::fs::read("test/scryfall/normal.json")
+            .await
+            .expect("Failed to read file");
+        let mut card: Card = serde_json::from_slice(&json).unwrap();
+        card.id = id.to_string();
+        card.power = Some("1+*".to_string());
+        card.toughness = Some("∞".to_string());
+
+        ingest::ingest(&mut conn, staging::LIVE, futures::stream::iter([Ok(card)]))
+            .await
+            .unwrap();
+
+        let mut cards = DbCard::load(&mut conn, &[id]).await.unwrap();
+        let face = &cards.remove(&id).unwrap().card_faces.unwrap()[0];
+        assert_eq!(face.power, Some(StatValue::parse("1+*")));
+        assert_eq!(face.toughness.as_ref().unwrap().text, "∞");
+
+        let values: (Option<f32>, Option<f32>) = sqlx::query_as(
+            "SELECT power_value, toughness_value FROM scryfall.card_faces WHERE card_id = $1",
+        )
+        .bind(id)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+        assert_eq!(values, (Some(1.0), None));
+
+        drop(conn);
+        db.close().await;
+    }
+}
@@ -0,0 +1,244 @@
+use futures::{Stream, TryStreamExt};
+use serde::de::DeserializeOwned;
+
+use super::Error;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum State {
+    #[default]
+    Before,
+    Inside,
+    After,
+}
+
+/// Splits a JSON array arriving in chunks into its elements.
+/// Only the element currently being scanned is kept in memory, so memory use is
+/// bounded by the largest element rather than the size of the array.
+#[derive(Debug, Default)]
+pub struct JsonArray {
+    buf: Vec<u8>,
+    /// Next byte to scan
+    pos: usize,
+    /// Start of the element being scanned
+    start: usize,
+
+    state: State,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// A comma was scanned, every element is then required, `[x,]` is not an array
+    separated: bool,
+}
+
+impl JsonArray {
+    /// Appends a chunk, dropping the bytes of elements already returned.
+    pub fn push(&mut self, chunk: &[u8]) {
+        self.buf.drain(..self.start);
+        self.pos -= self.start;
+        self.start = 0;
+
+        self.buf.extend_from_slice(chunk);
+    }
+
+    /// Returns the next complete element, or `None` if more input is needed.
+    pub fn next<T>(&mut self) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        while self.pos < self.buf.len() {
+            let b = self.buf[self.pos];
+            self.pos += 1;
+
+            match self.state {
+                State::Before => {
+                    match b {
+                        b'[' => {
+                            self.state = State::Inside;
+                            self.start = self.pos;
+                        }
+                        b if b.is_ascii_whitespace() => {}
+                        _ => return Err(Error::Bulk("expected a json array")),
+                    }
+                }
+                State::After => {
+                    if !b.is_ascii_whitespace() {
+                        return Err(Error::Bulk("trailing data after json array"));
+                    }
+                }
+                State::Inside if self.in_string => {
+                    match b {
+                        _ if self.escaped => self.escaped = false,
+                        b'\\' => self.escaped = true,
+                        b'"' => self.in_string = false,
+                        _ => {}
+                    }
+                }
+                State::Inside => {
+                    match b {
+                        b'"' => self.in_string = true,
+                        b'{' | b'[' => self.depth += 1,
+                        b']' if self.depth == 0 => {
+                            self.state = State::After;
+                            let element = self.element()?;
+                            if element.is_none() && self.separated {
+                                return Err(Error::Bulk("trailing comma in json array"));
+                            }
+                            return Ok(element);
+                        }
+                        b'}' | b']' => {
+                            self.depth = self
+                                .depth
+                                .checked_sub(1)
+                                .ok_or(Error::Bulk("unbalanced json array"))?;
+                        }
+                        b',' if self.depth == 0 => {
+                            let element = self.element()?;
+                            if element.is_none() {
+                                return Err(Error::Bulk("empty json array element"));
+                            }
+                            self.separated = true;
+                            return Ok(element);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Checks that the whole array was consumed once the input is exhausted.
+    pub fn finish(&self) -> Result<(), Error> {
+        if self.state == State::After {
+            Ok(())
+        } else {
+            Err(Error::Bulk("unexpected end of json array"))
+        }
+    }
+
+    /// Parses the element ending right before the current position.
+    fn element<T>(&mut self) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let raw = self.buf[self.start..self.pos - 1].trim_ascii();
+        let element = if raw.is_empty() {
+            None
+        } else {
+            Some(serde_json::from_slice(raw)?)
+        };
+
+        self.start = self.pos;
+        Ok(element)
+    }
+}
+
+/// Streams the elements of a JSON array from a stream of byte chunks.
+pub fn parse_array<T, S, B, E>(body: S) -> impl Stream<Item = Result<T, Error>>
+where
+    T: DeserializeOwned,
+    S: Stream<Item = Result<B, E>> + Unpin,
+    B: AsRef<[u8]>,
+    Error: From<E>,
+{
+    futures::stream::try_unfold((body, JsonArray::default()), |(mut body, mut array)| {
+        async move {
+            loop {
+                if let Some(element) = array.next()? {
+                    return Ok(Some((element, (body, array))));
+                }
+
+                let Some(chunk) = body.try_next().await? else {
+                    array.finish()?;
+                    return Ok(None);
+                };
+
+                array.push(chunk.as_ref());
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Element {
+        name: String,
+        values: Vec<u32>,
+    }
+
+    const ARRAY: &str = r#" [
+        {"name": "plain", "values": [1, 2]},
+        {"name": "brackets ] } [ {", "values": []},
+        {"name": "quotes \" and \\ , commas", "values": [3]}
+    ] "#;
+
+    fn collect(chunks: &[&[u8]]) -> Result<Vec<Element>, Error> {
+        let mut array = JsonArray::default();
+        let mut elements = vec![];
+
+        for chunk in chunks {
+            array.push(chunk);
+            while let Some(element) = array.next()? {
+                elements.push(element);
+            }
+        }
+
+        array.finish()?;
+        Ok(elements)
+    }
+
+    #[test]
+    fn test_json_array_chunk_boundaries() {
+        let expected = collect(&[ARRAY.as_bytes()]).unwrap();
+        assert_eq!(expected.len(), 3);
+        assert_eq!(expected[1].name, "brackets ] } [ {");
+        assert_eq!(expected[2].name, "quotes \" and \\ , commas");
+
+        for size in 1..ARRAY.len() {
+            let chunks = ARRAY.as_bytes().chunks(size).collect::<Vec<_>>();
+            assert_eq!(collect(&chunks).unwrap(), expected, "chunk size {size}");
+        }
+    }
+
+    #[test]
+    fn test_json_array_empty() {
+        assert!(collect(&[b"[]"]).unwrap().is_empty());
+        assert!(collect(&[b" [ \n ] "]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_json_array_malformed() {
+        assert!(collect(&[b"{}"]).is_err());
+        assert!(collect(&[br#"[{"name": "a", "values": []}"#]).is_err());
+        assert!(collect(&[br#"[{"name": "a", "values": []},,]"#]).is_err());
+        assert!(matches!(
+            collect(&[br#"[{"name": "a", "values": []}, ]"#]),
+            Err(Error::Bulk("trailing comma in json array"))
+        ));
+        assert!(collect(&[b"[,]"]).is_err());
+        assert!(collect(&[br#"[{"name": "a", "values": []}] x"#]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_array() {
+        let chunks = ARRAY
+            .as_bytes()
+            .chunks(7)
+            .map(Ok::<_, Error>)
+            .collect::<Vec<_>>();
+
+        let elements = parse_array::<Element, _, _, _>(futures::stream::iter(chunks))
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+
+        assert_eq!(elements.len(), 3);
+        assert_eq!(elements[0].values, vec![1, 2]);
+    }
+}
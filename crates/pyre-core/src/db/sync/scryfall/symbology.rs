@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use tracing::warn;
+
+use super::Error;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SymbolList {
+    pub data: Vec<Symbol>,
+}
+
+#[allow(clippy::struct_field_names)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Symbol {
+    /// e.g. "{W/U}"
+    pub symbol: String,
+    /// Not available for a few unofficial symbols
+    pub svg_uri: Option<String>,
+    /// e.g. "one white or blue mana"
+    pub english: String,
+    /// Null for symbols without a mana value, e.g. "{T}" in older responses
+    pub mana_value: Option<f64>,
+}
+
+/// Upserts all symbols with an svg, returns the number of rows written.
+pub async fn upsert(
+    conn: &mut PgConnection,
+    schema: &str,
+    symbols: SymbolList,
+) -> Result<u64, Error> {
+    let (symbols, missing): (Vec<_>, Vec<_>) =
+        symbols.data.into_iter().partition(|s| s.svg_uri.is_some());
+
+    if !missing.is_empty() {
+        warn!(
+            symbols = ?missing.iter().map(|s| &s.symbol).collect::<Vec<_>>(),
+            "skipping symbols without svg"
+        );
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    let cmcs = symbols
+        .iter()
+        .map(|s| s.mana_value.map(|v| v as f32))
+        .collect::<Vec<_>>();
+
+    let res = sqlx::query(&format!(
+        r"
+        INSERT INTO {schema}.symbols (symbol, svg_uri, description, cmc)
+        SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[], $4::real[])
+        ON CONFLICT (symbol) DO UPDATE SET
+            svg_uri = EXCLUDED.svg_uri,
+            description = EXCLUDED.description,
+            cmc = EXCLUDED.cmc
+        "
+    ))
+    .bind(
+        symbols
+            .iter()
+            .map(|s| s.symbol.as_str())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        symbols
+            .iter()
+            .map(|s| s.svg_uri.as_deref())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        symbols
+            .iter()
+            .map(|s| s.english.as_str())
+            .collect::<Vec<_>>(),
+    )
+    .bind(cmcs)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(res.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::Connection;
+
+    use super::*;
+    use crate::db::{sync::scryfall::staging, tests::TestDb};
+
+    #[tokio::test]
+    async fn test_symbology_upsert() {
+        let json = tokio::fs::read("test/scryfall/symbology.json")
+            .await
+            .expect("Failed to read file");
+        let symbols: SymbolList = serde_json::from_slice(&json).unwrap();
+        assert_eq!(symbols.data.len(), 5);
+
+        let db = TestDb::new().await;
+        let mut conn = PgConnection::connect(&db.pg).await.unwrap();
+
+        assert_eq!(upsert(&mut conn, staging::LIVE, symbols).await.unwrap(), 5);
+
+        let cmc: Option<f32> =
+            sqlx::query_scalar("SELECT cmc FROM scryfall.symbols WHERE symbol = '{2}'")
+                .fetch_one(&mut conn)
+                .await
+                .unwrap();
+        assert_eq!(cmc, Some(2.0));
+
+        conn.close().await.unwrap();
+        db.close().await;
+    }
+}
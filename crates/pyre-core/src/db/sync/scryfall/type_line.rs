@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+
+/// Supertypes from the comprehensive rules, plus the ones of Un-sets and old frames
+const SUPERTYPES: &[&str] = &[
+    "Basic",
+    "Elite",
+    "Host",
+    "Legendary",
+    "Ongoing",
+    "Snow",
+    "Token",
+    "World",
+];
+
+/// Subtypes of more than one word, everything else splits on whitespace
+const MULTI_WORD_SUBTYPES: &[&str] = &["Time Lord"];
+
+/// A type line such as `Legendary Creature — Elf Druid`, split into its parts.
+///
+/// Type lines of several faces, e.g. `Instant // Sorcery`, combine the types of every part.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypeLine {
+    /// e.g. `Legendary`, `Basic` or `Snow`
+    pub supertypes: Vec<String>,
+    /// e.g. `Creature`, `Artifact` or `Land`, anything before the dash that is not a supertype
+    pub types: Vec<String>,
+    /// e.g. `Elf`, `Equipment` or `Forest`
+    pub subtypes: Vec<String>,
+}
+
+impl TypeLine {
+    pub fn parse(type_line: &str) -> Self {
+        let mut parsed = Self::default();
+
+        for part in type_line.split("//") {
+            let (types, subtypes) = part.split_once('—').unwrap_or((part, ""));
+
+            for word in types.split_whitespace() {
+                let target = if SUPERTYPES.contains(&word) {
+                    &mut parsed.supertypes
+                } else {
+                    &mut parsed.types
+                };
+                push_unique(target, word);
+            }
+
+            for subtype in split_subtypes(subtypes) {
+                push_unique(&mut parsed.subtypes, subtype);
+            }
+        }
+
+        parsed
+    }
+}
+
+/// Splits on whitespace, keeping the subtypes of [`MULTI_WORD_SUBTYPES`] whole.
+fn split_subtypes(mut text: &str) -> Vec<&str> {
+    let mut subtypes = vec![];
+
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return subtypes;
+        }
+
+        let len = MULTI_WORD_SUBTYPES
+            .iter()
+            .find(|s| {
+                text.strip_prefix(**s)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+            })
+            .map_or_else(
+                || text.find(char::is_whitespace).unwrap_or(text.len()),
+                |s| s.len(),
+            );
+
+        subtypes.push(&text[..len]);
+        text = &text[len..];
+    }
+}
+
+fn push_unique(target: &mut Vec<String>, value: &str) {
+    if !target.iter().any(|v| v == value) {
+        target.push(value.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parsed(type_line: &str) -> (Vec<String>, Vec<String>, Vec<String>) {
+        let t = TypeLine::parse(type_line);
+        (t.supertypes, t.types, t.subtypes)
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_type_line() {
+        assert_eq!(
+            parsed("Legendary Creature — Elf Druid"),
+            (
+                strings(&["Legendary"]),
+                strings(&["Creature"]),
+                strings(&["Elf", "Druid"])
+            )
+        );
+        assert_eq!(
+            parsed("Basic Snow Land — Forest"),
+            (
+                strings(&["Basic", "Snow"]),
+                strings(&["Land"]),
+                strings(&["Forest"])
+            )
+        );
+        assert_eq!(parsed("Artifact"), (vec![], strings(&["Artifact"]), vec![]));
+        assert_eq!(
+            parsed("Legendary Creature — Time Lord Doctor"),
+            (
+                strings(&["Legendary"]),
+                strings(&["Creature"]),
+                strings(&["Time Lord", "Doctor"])
+            )
+        );
+        assert_eq!(parsed(""), (vec![], vec![], vec![]));
+    }
+
+    #[test]
+    fn test_type_line_faces() {
+        assert_eq!(
+            parsed("Creature — Human Wizard // Creature — Human Insect"),
+            (
+                vec![],
+                strings(&["Creature"]),
+                strings(&["Human", "Wizard", "Insect"])
+            )
+        );
+        assert_eq!(
+            parsed("Instant — Adventure // Sorcery"),
+            (
+                vec![],
+                strings(&["Instant", "Sorcery"]),
+                strings(&["Adventure"])
+            )
+        );
+    }
+}
@@ -2,7 +2,7 @@ use std::{path::PathBuf, process::exit};
 
 use clap::Parser;
 use config::HasTelemetry;
-use db::sync;
+use db::{migrate, sync};
 use garde::Validate;
 use opentelemetry::KeyValue;
 use pyre_build::build_info;
@@ -14,6 +14,7 @@ use tracing::error;
 use uuid::Uuid;
 
 mod auth;
+mod card;
 mod config;
 mod db;
 mod error;
@@ -27,12 +28,14 @@ enum CliMode {
     #[default]
     Server,
     DbSync,
+    Migrate,
 }
 
 /// Pyre CLI: A command line interface for Pyre
-/// There are two modes: server and dbsync
+/// There are three modes: server, dbsync and migrate
 /// The server mode starts the Pyre server
 /// The dbsync mode syncs the database with scryfall
+/// The migrate mode applies the embedded database migrations
 #[derive(Parser, Debug, Default)]
 #[command(version, about, long_about = None)]
 struct Cli {
@@ -43,6 +46,26 @@ struct Cli {
     /// Path to the config file
     #[arg(short, long)]
     config: Option<PathBuf>,
+
+    /// Run a single sync and exit, dbsync mode only
+    #[arg(long)]
+    once: bool,
+
+    /// Report what a sync would change without writing, dbsync mode only
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Write the dry-run diff as JSON to this file
+    #[arg(long, requires = "dry_run")]
+    diff: Option<PathBuf>,
+
+    /// Put the data of the sync before the last one back live, dbsync mode only
+    #[arg(long, conflicts_with_all = ["once", "dry_run"])]
+    rollback: bool,
+
+    /// What to do with the migrations, migrate mode only
+    #[arg(long, default_value_t = migrate::Action::Apply)]
+    migrate: migrate::Action,
 }
 
 #[tokio::main(flavor = "multi_thread")]
@@ -69,8 +92,19 @@ async fn main() -> color_eyre::Result<()> {
             svc::start(cfg, shutdown.subscribe()).await?;
         }
         CliMode::DbSync => {
+            let opts = sync::Options {
+                once: cli.once,
+                dry_run: cli.dry_run,
+                diff: cli.diff.clone(),
+                rollback: cli.rollback,
+            };
             let cfg = load_config::<sync::config::Config>(cli).await?;
-            sync::start(cfg, shutdown.subscribe()).await?;
+            sync::start(cfg, opts, shutdown.subscribe()).await?;
+        }
+        CliMode::Migrate => {
+            let action = cli.migrate;
+            let cfg = load_config::<migrate::Config>(cli).await?;
+            migrate::start(cfg, action).await?;
         }
     }
 
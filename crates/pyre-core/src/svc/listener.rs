@@ -0,0 +1,119 @@
+use std::sync::Arc;
+
+use sqlx::postgres::PgListener;
+use tracing::{info, warn};
+
+use crate::{
+    card::cache::CardCache,
+    db::{
+        self,
+        sync::scryfall::run::{self, SyncRun},
+    },
+};
+
+/// Wait before retrying after a database error
+const RETRY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Invalidates the card cache whenever dbsync puts new data live, until shutdown.
+///
+/// Notifications sent while the connection is down are lost, so the version is read
+/// again after every reconnect.
+pub async fn listen(
+    sql: sqlx::PgPool,
+    cards: Arc<CardCache>,
+    mut shutdown: tokio::sync::broadcast::Receiver<()>,
+) -> Result<(), db::Error> {
+    let mut listener = PgListener::connect_with(&sql).await?;
+    listener.listen(run::SYNCED).await?;
+    info!(channel = run::SYNCED, version = ?cards.version(), "listening for synced card data");
+
+    // Set when notifications may have been missed, the version is then read from the database
+    let mut resync = false;
+
+    loop {
+        if resync {
+            match SyncRun::live_version(sql.clone()).await {
+                Ok(version) => {
+                    resync = false;
+                    synced(&cards, version);
+                }
+                Err(e) => {
+                    warn!(%e, "failed to read the live data version, retrying");
+                    tokio::time::sleep(RETRY).await;
+                    continue;
+                }
+            }
+        }
+
+        let notification = tokio::select! {
+            _ = shutdown.recv() => break,
+            res = listener.try_recv() => res,
+        };
+
+        match notification {
+            Ok(Some(notification)) => {
+                match notification.payload().parse() {
+                    Ok(version) => synced(&cards, Some(version)),
+                    Err(e) => {
+                        warn!(%e, payload = notification.payload(), "invalid synced version");
+                        resync = true;
+                    }
+                }
+            }
+            // Reconnects on the next call
+            Ok(None) => {
+                warn!("lost the sync listener connection, reconnecting");
+                resync = true;
+            }
+            Err(e) => {
+                warn!(%e, "sync listener failed, retrying");
+                tokio::time::sleep(RETRY).await;
+            }
+        }
+    }
+
+    info!("sync listener shutting down");
+    Ok(())
+}
+
+/// Invalidates the card cache if `version` is not the cached one.
+fn synced(cards: &CardCache, version: Option<i32>) {
+    if version != cards.version() {
+        cards.invalidate(version);
+        info!(version = ?version, "card data synced, invalidated card cache");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::sync::scryfall::staging::tests::SYNC, svc::state::DbConfig};
+
+    #[tokio::test]
+    async fn test_listen() {
+        let _sync = SYNC.lock().await;
+        let sql = sqlx::PgPool::connect(&DbConfig::default().pg)
+            .await
+            .unwrap();
+        let cards = Arc::new(CardCache::new(8, Some(1)));
+        let id = uuid::Uuid::new_v4();
+        cards.insert(Some(1), id, "{}".into());
+
+        let (tx, shutdown) = tokio::sync::broadcast::channel(1);
+        let task = tokio::spawn(listen(sql.clone(), cards.clone(), shutdown));
+        tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+
+        SyncRun::notify(sql.clone(), i32::MAX).await.unwrap();
+        for _ in 0..50 {
+            if cards.version() == Some(i32::MAX) {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(cards.version(), Some(i32::MAX));
+        assert!(cards.get(&id).is_none());
+
+        tx.send(()).unwrap();
+        task.await.unwrap().unwrap();
+    }
+}
@@ -1,6 +1,10 @@
 use std::sync::Arc;
 
-use axum::{extract::Request, http::HeaderName, Router};
+use axum::{
+    extract::{Request, State},
+    http::{HeaderName, HeaderValue, Response},
+    Router,
+};
 use axum_login::{
     tower_sessions::{Expiry, SessionManagerLayer},
     AuthManagerLayerBuilder,
@@ -26,6 +30,8 @@ use super::state::AppState;
 use crate::{auth::session::SessionBackend, config::Config};
 
 pub const X_REQUEST_ID: HeaderName = HeaderName::from_static("x-request-id");
+/// Sync run the served card data comes from
+pub const X_DATA_VERSION: HeaderName = HeaderName::from_static("x-data-version");
 
 pub async fn router_with_middlewares(
     router: Router<AppState>,
@@ -77,6 +83,15 @@ pub async fn router_with_middlewares(
     Ok(router.layer(middlewares).with_state(state))
 }
 
+/// Sets [`X_DATA_VERSION`], only layered on the routes serving card data.
+pub async fn data_version<B>(State(state): State<AppState>, mut res: Response<B>) -> Response<B> {
+    if let Some(version) = state.cards.version() {
+        res.headers_mut()
+            .insert(X_DATA_VERSION, HeaderValue::from(version));
+    }
+    res
+}
+
 fn make_span<B>(request: &Request<B>, level: &str) -> Span {
     let request_id = request
         .extensions()
@@ -16,14 +16,16 @@ use serde::{Deserialize, Serialize};
 use server::Http;
 use state::AppState;
 use tower_governor::GovernorLayer;
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
     auth::{self, session::SessionBackend},
+    card,
     config::Config,
 };
 
 pub mod limiter;
+pub mod listener;
 pub mod middleware;
 pub mod server;
 pub mod state;
@@ -59,9 +61,35 @@ pub async fn start(
         )?
     };
 
+    crate::db::migrate::startup(&cfg.db).await?;
     let state = AppState::new(cfg.clone()).await?;
     let addr: SocketAddr = cfg.server.addr.parse()?;
 
+    let listener = tokio::spawn(listener::listen(
+        state.sql_pool.clone(),
+        state.cards.clone(),
+        shutdown.resubscribe(),
+    ));
+
+    // Served from the synced card data, responses tell which sync it comes from
+    let card_data = Router::new()
+        .route("/cards/search", get(card::search::search))
+        .route("/cards/{id}", get(card::get))
+        .route("/cards/{id}/prices", get(card::prices))
+        .route("/cards/{id}/image", get(card::image::face))
+        .route("/cards/{id}/legalities", get(card::legalities))
+        .route("/formats/{format}/changes", get(card::format::changes))
+        .route("/oracle", get(card::oracle::search))
+        .route("/oracle/{oracle_id}", get(card::oracle::get))
+        .route(
+            "/oracle/{oracle_id}/printings",
+            get(card::oracle::printings),
+        )
+        .layer(axum::middleware::map_response_with_state(
+            state.clone(),
+            middleware::data_version,
+        ));
+
     let router = Router::new()
         .route("/me", post(auth::me))
         .route_layer(login_required!(
@@ -70,6 +98,8 @@ pub async fn start(
         ))
         .route("/oauth2/discord", get(auth::provider::discord::redirect))
         .route("/oauth2/discord/auth", get(auth::provider::discord::auth))
+        .merge(card_data)
+        .route("/images/{hash}", get(card::image::get))
         .route("/", get(root))
         .layer(GovernorLayer {
             config: limiter::setup(&cfg.http, UserIdKeyExtractor::<SessionBackend>::new()),
@@ -83,6 +113,9 @@ pub async fn start(
         .with_http3()?;
 
     http.join_set.join_all().await;
+    if let Err(e) = listener.await? {
+        warn!(%e, "sync listener failed");
+    }
     state.shutdown().await;
 
     Ok(())
@@ -0,0 +1,161 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use tracing::debug;
+
+/// Length of a hex encoded blake3 hash
+const HASH_LEN: usize = 64;
+
+/// Distinguishes temporary files of concurrent writes within a process
+static NEXT_TMP: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid content hash: {0}")]
+    InvalidHash(String),
+
+    #[error("content store io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// A content-addressed store on disk.
+/// Blobs are keyed by the blake3 hash of their content and stored under `root/ab/<hash>`,
+/// so a blob never changes once written and identical content is only stored once.
+#[derive(Debug, Clone)]
+pub struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    #[must_use]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Hex encoded hash of a blob, the key it is stored under.
+    #[must_use]
+    pub fn hash(bytes: &[u8]) -> String {
+        blake3::hash(bytes).to_hex().to_string()
+    }
+
+    /// Path of the blob with the given hash, whether or not it exists.
+    ///
+    /// # Errors
+    /// If the hash is not a lowercase hex encoded blake3 hash.
+    pub fn path(&self, hash: &str) -> Result<PathBuf, Error> {
+        let valid = hash.len() == HASH_LEN
+            && hash
+                .bytes()
+                .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+
+        if !valid {
+            return Err(Error::InvalidHash(hash.to_string()));
+        }
+
+        Ok(self.root.join(&hash[..2]).join(hash))
+    }
+
+    /// Whether the blob with the given hash is stored.
+    ///
+    /// # Errors
+    /// If the hash is invalid or the store cannot be read.
+    pub async fn contains(&self, hash: &str) -> Result<bool, Error> {
+        Ok(tokio::fs::try_exists(self.path(hash)?).await?)
+    }
+
+    /// Stores a blob and returns its hash, content already stored is not written again.
+    /// The blob is written to a temporary file and renamed, so readers never see partial content.
+    ///
+    /// # Errors
+    /// If the blob cannot be written.
+    pub async fn put(&self, bytes: &[u8]) -> Result<String, Error> {
+        let hash = Self::hash(bytes);
+        let path = self.path(&hash)?;
+
+        if tokio::fs::try_exists(&path).await? {
+            debug!(%hash, "blob already stored");
+            return Ok(hash);
+        }
+
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+
+        let tmp = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            NEXT_TMP.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        if let Err(e) = tokio::fs::write(&tmp, bytes).await {
+            let _ = tokio::fs::remove_file(&tmp).await;
+            return Err(e.into());
+        }
+        tokio::fs::rename(&tmp, &path).await?;
+
+        debug!(%hash, size = bytes.len(), "stored blob");
+        Ok(hash)
+    }
+
+    /// Opens the blob with the given hash, `None` if it is not stored.
+    ///
+    /// # Errors
+    /// If the hash is invalid or the blob cannot be opened.
+    pub async fn open(&self, hash: &str) -> Result<Option<tokio::fs::File>, Error> {
+        match tokio::fs::File::open(self.path(hash)?).await {
+            Ok(file) => Ok(Some(file)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_store() {
+        let root = std::env::temp_dir().join(format!("pyre-cas-{}", std::process::id()));
+        let store = Store::new(&root);
+
+        let hash = store.put(b"pyre").await.unwrap();
+        assert_eq!(hash, Store::hash(b"pyre"));
+        assert!(store.contains(&hash).await.unwrap());
+        assert!(store
+            .path(&hash)
+            .unwrap()
+            .starts_with(root.join(&hash[..2])));
+
+        // Identical content is stored once under the same key
+        assert_eq!(store.put(b"pyre").await.unwrap(), hash);
+
+        let mut content = vec![];
+        store
+            .open(&hash)
+            .await
+            .unwrap()
+            .unwrap()
+            .read_to_end(&mut content)
+            .await
+            .unwrap();
+        assert_eq!(content, b"pyre");
+
+        let missing = Store::hash(b"missing");
+        assert!(!store.contains(&missing).await.unwrap());
+        assert!(store.open(&missing).await.unwrap().is_none());
+
+        assert!(store.path("../etc/passwd").is_err());
+        assert!(store.path(&hash.to_uppercase()).is_err());
+
+        tokio::fs::remove_dir_all(root).await.unwrap();
+    }
+}
@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 
+pub mod cas;
 pub mod fs;
 pub mod toml;
 
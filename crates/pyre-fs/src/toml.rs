@@ -4,7 +4,7 @@ use std::{
 };
 
 use serde::de::DeserializeOwned;
-use tracing::{error, info};
+use tracing::info;
 
 use super::DefaultPathProvider;
 
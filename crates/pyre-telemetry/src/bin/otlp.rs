@@ -1,6 +1,6 @@
 use std::{self, net::SocketAddr, process::exit, sync::Arc};
 
-use clap::{command, Parser, Subcommand};
+use clap::{Parser, Subcommand};
 use opentelemetry_proto::tonic::{
     collector::{
         metrics::v1::{
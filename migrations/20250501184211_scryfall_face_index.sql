@@ -0,0 +1,5 @@
+-- Faces are upserted by their position on the card, so colors and other
+-- face relations keep pointing at the same row across syncs
+ALTER TABLE scryfall.card_faces
+    ADD COLUMN face_index SMALLINT NOT NULL DEFAULT 0,
+    ADD CONSTRAINT card_faces_card_id_face_index_key UNIQUE (card_id, face_index);
@@ -0,0 +1,18 @@
+-- Outcome of a dbsync run
+CREATE TYPE sync_outcome AS ENUM ('running', 'success', 'skipped', 'failed', 'cancelled');
+
+-- Every dbsync run, bulk metadata is null if it could not be fetched
+CREATE TABLE scryfall.sync_runs (
+    id SERIAL PRIMARY KEY,
+    bulk_id UUID,
+    bulk_updated_at TIMESTAMP WITH TIME ZONE,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMP WITH TIME ZONE,
+    cards BIGINT NOT NULL DEFAULT 0,
+    faces BIGINT NOT NULL DEFAULT 0,
+    sets BIGINT NOT NULL DEFAULT 0,
+    outcome sync_outcome NOT NULL DEFAULT 'running',
+    error TEXT
+);
+
+CREATE INDEX idx_sync_runs_outcome ON scryfall.sync_runs(outcome, bulk_updated_at);
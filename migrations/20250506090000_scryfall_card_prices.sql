@@ -0,0 +1,16 @@
+-- Price dimensions
+CREATE TYPE price_finish AS ENUM ('nonfoil', 'foil', 'etched');
+CREATE TYPE price_currency AS ENUM ('usd', 'eur', 'tix');
+
+-- Price history, a row is appended by dbsync whenever a price changes.
+-- No foreign key to cards so the history outlives card rows.
+CREATE TABLE scryfall.card_prices (
+    card_id UUID NOT NULL,
+    finish price_finish NOT NULL,
+    currency price_currency NOT NULL,
+    price REAL,
+    observed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (card_id, finish, currency, observed_at)
+);
+
+CREATE INDEX idx_card_prices_observed_at ON scryfall.card_prices(observed_at);
@@ -0,0 +1,12 @@
+-- Set metadata from the Scryfall /sets endpoint
+ALTER TABLE scryfall.sets
+    ADD COLUMN released_at DATE,
+    ADD COLUMN card_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN parent_set_code VARCHAR(10),
+    ADD COLUMN block_code VARCHAR(10),
+    ADD COLUMN block VARCHAR(100),
+    ADD COLUMN digital BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN icon_svg_uri TEXT;
+
+CREATE INDEX idx_sets_released_at ON scryfall.sets(released_at);
+CREATE INDEX idx_sets_parent_set_code ON scryfall.sets(parent_set_code);
@@ -0,0 +1,17 @@
+-- Rulings are shared by every print of a card, linked through the oracle id
+ALTER TABLE scryfall.cards ADD COLUMN oracle_id UUID;
+
+CREATE INDEX idx_cards_oracle_id ON scryfall.cards(oracle_id);
+
+-- Rulings from the Scryfall rulings bulk file, replaced on every sync
+CREATE TABLE scryfall.rulings (
+    id SERIAL PRIMARY KEY,
+    oracle_id UUID NOT NULL,
+    source VARCHAR(20) NOT NULL,
+    published_at DATE NOT NULL,
+    comment TEXT NOT NULL
+);
+
+CREATE INDEX idx_rulings_oracle_id ON scryfall.rulings(oracle_id, published_at);
+
+ALTER TABLE scryfall.sync_runs ADD COLUMN rulings BIGINT NOT NULL DEFAULT 0;
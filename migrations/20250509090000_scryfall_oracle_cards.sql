@@ -0,0 +1,19 @@
+-- One row per unique card, printings link to it through cards.oracle_id
+CREATE TABLE scryfall.oracle_cards (
+    oracle_id UUID PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    -- Printing shown when the card is listed once, picked by dbsync
+    canonical_card_id UUID REFERENCES scryfall.cards(id) ON DELETE SET NULL
+);
+
+CREATE INDEX idx_oracle_cards_name ON scryfall.oracle_cards(lower(name));
+
+INSERT INTO scryfall.oracle_cards (oracle_id, name)
+SELECT DISTINCT ON (oracle_id) oracle_id, name
+FROM scryfall.cards
+WHERE oracle_id IS NOT NULL
+ORDER BY oracle_id, released_at DESC;
+
+ALTER TABLE scryfall.cards
+    ADD CONSTRAINT cards_oracle_id_fkey
+    FOREIGN KEY (oracle_id) REFERENCES scryfall.oracle_cards(oracle_id);
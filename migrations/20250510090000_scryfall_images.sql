@@ -0,0 +1,12 @@
+-- Card images mirrored on disk, keyed by the Scryfall URI they were downloaded from.
+-- The content lives in a content-addressed store under its blake3 hash, a new scan of a
+-- card gets a new URI and so a new row, while identical images share the same blob.
+CREATE TABLE scryfall.images (
+    uri TEXT PRIMARY KEY,
+    hash CHAR(64) NOT NULL,
+    content_type VARCHAR(50) NOT NULL,
+    size BIGINT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_images_hash ON scryfall.images(hash);
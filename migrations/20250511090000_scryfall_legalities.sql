@@ -0,0 +1,80 @@
+-- Legality of a card in a format
+CREATE TYPE legality AS ENUM ('legal', 'not_legal', 'banned', 'restricted');
+
+-- Formats are added by dbsync as Scryfall publishes them
+CREATE TABLE scryfall.formats (
+    id SERIAL PRIMARY KEY,
+    name VARCHAR(50) UNIQUE NOT NULL
+);
+
+CREATE TABLE scryfall.card_legalities (
+    card_id UUID REFERENCES scryfall.cards(id) ON DELETE CASCADE,
+    format_id INTEGER REFERENCES scryfall.formats(id) ON DELETE CASCADE,
+    status legality NOT NULL,
+    PRIMARY KEY (card_id, format_id)
+);
+
+CREATE INDEX idx_card_legalities_format ON scryfall.card_legalities(format_id, status);
+
+-- Backfill from the boolean columns, banned and restricted were folded into them
+-- so they come back as not_legal and legal until the next sync
+INSERT INTO scryfall.formats (name)
+VALUES
+    ('standard'), ('future'), ('historic'), ('timeless'), ('gladiator'), ('pioneer'),
+    ('explorer'), ('modern'), ('legacy'), ('pauper'), ('vintage'), ('penny'), ('commander'),
+    ('oathbreaker'), ('standardbrawl'), ('brawl'), ('alchemy'), ('paupercommander'), ('duel'),
+    ('oldschool'), ('premodern'), ('predh');
+
+INSERT INTO scryfall.card_legalities (card_id, format_id, status)
+SELECT c.id, f.id, CASE WHEN l.legal THEN 'legal'::legality ELSE 'not_legal'::legality END
+FROM scryfall.cards c
+CROSS JOIN LATERAL (
+    VALUES
+        ('standard', c.legality_standard),
+        ('future', c.legality_future),
+        ('historic', c.legality_historic),
+        ('timeless', c.legality_timeless),
+        ('gladiator', c.legality_gladiator),
+        ('pioneer', c.legality_pioneer),
+        ('explorer', c.legality_explorer),
+        ('modern', c.legality_modern),
+        ('legacy', c.legality_legacy),
+        ('pauper', c.legality_pauper),
+        ('vintage', c.legality_vintage),
+        ('penny', c.legality_penny),
+        ('commander', c.legality_commander),
+        ('oathbreaker', c.legality_oathbreaker),
+        ('standardbrawl', c.legality_standardbrawl),
+        ('brawl', c.legality_brawl),
+        ('alchemy', c.legality_alchemy),
+        ('paupercommander', c.legality_paupercommander),
+        ('duel', c.legality_duel),
+        ('oldschool', c.legality_oldschool),
+        ('premodern', c.legality_premodern),
+        ('predh', c.legality_predh)
+) AS l(format, legal)
+JOIN scryfall.formats f ON f.name = l.format;
+
+ALTER TABLE scryfall.cards
+    DROP COLUMN legality_standard,
+    DROP COLUMN legality_future,
+    DROP COLUMN legality_historic,
+    DROP COLUMN legality_timeless,
+    DROP COLUMN legality_gladiator,
+    DROP COLUMN legality_pioneer,
+    DROP COLUMN legality_explorer,
+    DROP COLUMN legality_modern,
+    DROP COLUMN legality_legacy,
+    DROP COLUMN legality_pauper,
+    DROP COLUMN legality_vintage,
+    DROP COLUMN legality_penny,
+    DROP COLUMN legality_commander,
+    DROP COLUMN legality_oathbreaker,
+    DROP COLUMN legality_standardbrawl,
+    DROP COLUMN legality_brawl,
+    DROP COLUMN legality_alchemy,
+    DROP COLUMN legality_paupercommander,
+    DROP COLUMN legality_duel,
+    DROP COLUMN legality_oldschool,
+    DROP COLUMN legality_premodern,
+    DROP COLUMN legality_predh;
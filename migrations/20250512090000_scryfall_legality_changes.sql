@@ -0,0 +1,13 @@
+-- A change of a card's status in a format, recorded by dbsync when a sync changes it.
+-- No foreign keys so the history outlives card rows, the format is kept by name.
+CREATE TABLE scryfall.legality_changes (
+    id BIGSERIAL PRIMARY KEY,
+    card_id UUID NOT NULL,
+    format VARCHAR(50) NOT NULL,
+    old_status legality NOT NULL,
+    new_status legality NOT NULL,
+    changed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_legality_changes_format ON scryfall.legality_changes(format, changed_at);
+CREATE INDEX idx_legality_changes_card ON scryfall.legality_changes(card_id, format, changed_at);
@@ -0,0 +1,17 @@
+-- Stats are kept as printed, e.g. '*', '1+*', 'X' or '∞', with the number they compare as
+ALTER TABLE scryfall.card_faces
+    ALTER COLUMN power TYPE TEXT USING power::TEXT,
+    ALTER COLUMN toughness TYPE TEXT USING toughness::TEXT,
+    ALTER COLUMN loyalty TYPE TEXT USING loyalty::TEXT,
+    ADD COLUMN power_value REAL,
+    ADD COLUMN toughness_value REAL,
+    ADD COLUMN loyalty_value REAL;
+
+UPDATE scryfall.card_faces
+SET power_value = power::REAL,
+    toughness_value = toughness::REAL,
+    loyalty_value = loyalty::REAL;
+
+CREATE INDEX idx_card_faces_power_value ON scryfall.card_faces(power_value);
+CREATE INDEX idx_card_faces_toughness_value ON scryfall.card_faces(toughness_value);
+CREATE INDEX idx_card_faces_loyalty_value ON scryfall.card_faces(loyalty_value);
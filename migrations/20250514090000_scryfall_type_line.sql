@@ -0,0 +1,33 @@
+-- Type lines split into their parts, e.g. 'Legendary Creature — Elf Druid'
+ALTER TABLE scryfall.card_faces
+    ADD COLUMN supertypes TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN types TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN subtypes TEXT[] NOT NULL DEFAULT '{}';
+
+-- Best effort until the next sync parses them, multi-word subtypes are split on spaces
+CREATE FUNCTION pg_temp.type_words(type_line TEXT, side INTEGER) RETURNS TEXT[] AS $$
+    SELECT COALESCE(array_agg(w ORDER BY n), '{}')
+    FROM (
+        SELECT w, MIN(n) AS n
+        FROM regexp_split_to_table(type_line, '//') WITH ORDINALITY AS p(part, i),
+            regexp_split_to_table(trim(split_part(part, '—', side)), '\s+') WITH ORDINALITY AS s(w, j),
+            LATERAL (SELECT i * 1000 + j) AS o(n)
+        WHERE w <> ''
+        GROUP BY w
+    ) words
+$$ LANGUAGE SQL IMMUTABLE;
+
+UPDATE scryfall.card_faces
+SET supertypes = ARRAY(
+        SELECT w FROM unnest(pg_temp.type_words(type_line, 1)) w
+        WHERE w = ANY('{Basic,Elite,Host,Legendary,Ongoing,Snow,Token,World}')
+    ),
+    types = ARRAY(
+        SELECT w FROM unnest(pg_temp.type_words(type_line, 1)) w
+        WHERE w <> ALL('{Basic,Elite,Host,Legendary,Ongoing,Snow,Token,World}')
+    ),
+    subtypes = pg_temp.type_words(type_line, 2);
+
+CREATE INDEX idx_card_faces_supertypes ON scryfall.card_faces USING gin(supertypes);
+CREATE INDEX idx_card_faces_types ON scryfall.card_faces USING gin(types);
+CREATE INDEX idx_card_faces_subtypes ON scryfall.card_faces USING gin(subtypes);
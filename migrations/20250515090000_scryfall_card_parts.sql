@@ -0,0 +1,14 @@
+-- How a related card belongs with a card
+CREATE TYPE part_component AS ENUM ('token', 'meld_part', 'meld_result', 'combo_piece');
+
+-- Scryfall's all_parts, the related card is not necessarily synced so it has no FK
+CREATE TABLE scryfall.card_parts (
+    card_id UUID REFERENCES scryfall.cards(id) ON DELETE CASCADE,
+    part_id UUID NOT NULL,
+    component part_component NOT NULL,
+    name TEXT NOT NULL,
+    type_line TEXT NOT NULL,
+    PRIMARY KEY (card_id, part_id)
+);
+
+CREATE INDEX idx_card_parts_part ON scryfall.card_parts(part_id);
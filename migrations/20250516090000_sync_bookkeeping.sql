@@ -0,0 +1,11 @@
+-- Run history and the image index describe dbsync itself, not the card data. Out of the
+-- scryfall schema, a swap or a rollback of the card data leaves them as they are.
+ALTER TABLE scryfall.sync_runs SET SCHEMA public;
+ALTER TABLE scryfall.images SET SCHEMA public;
+
+-- The copies made by earlier syncs are never read
+DROP TABLE IF EXISTS scryfall_next.sync_runs, scryfall_next.images;
+DROP TABLE IF EXISTS scryfall_prev.sync_runs, scryfall_prev.images;
+
+-- A rollback is recorded as a run of its own, the data version it puts live
+ALTER TYPE sync_outcome ADD VALUE 'rollback';
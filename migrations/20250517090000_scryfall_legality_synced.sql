@@ -0,0 +1,3 @@
+-- Whether the status was written by a sync. The backfill folded banned and restricted into
+-- not_legal and legal, a change from a status no sync wrote is not recorded as a change.
+ALTER TABLE scryfall.card_legalities ADD COLUMN synced BOOLEAN NOT NULL DEFAULT false;
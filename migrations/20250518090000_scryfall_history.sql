@@ -0,0 +1,9 @@
+-- Price and legality history is appended by each sync, copying it into the staging schema
+-- grew every sync with the whole history. Out of the scryfall schema it is written in place,
+-- a swap or a rollback of the card data leaves it as it is.
+ALTER TABLE scryfall.card_prices SET SCHEMA public;
+ALTER TABLE scryfall.legality_changes SET SCHEMA public;
+
+-- The copies made by earlier syncs are never read
+DROP TABLE IF EXISTS scryfall_next.card_prices, scryfall_next.legality_changes;
+DROP TABLE IF EXISTS scryfall_prev.card_prices, scryfall_prev.legality_changes;
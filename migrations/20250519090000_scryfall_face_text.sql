@@ -0,0 +1,18 @@
+-- Faces without a mana cost, type line or rules text are synced with empty ones, and a face
+-- without a mana value with 0. Rows written before that are filled in the same way.
+UPDATE scryfall.card_faces
+SET mana_cost = COALESCE(mana_cost, ''),
+    cmc = COALESCE(cmc, 0),
+    type_line = COALESCE(type_line, ''),
+    oracle_text = COALESCE(oracle_text, '')
+WHERE mana_cost IS NULL OR cmc IS NULL OR type_line IS NULL OR oracle_text IS NULL;
+
+ALTER TABLE scryfall.card_faces
+    ALTER COLUMN mana_cost SET DEFAULT '',
+    ALTER COLUMN mana_cost SET NOT NULL,
+    ALTER COLUMN cmc SET DEFAULT 0,
+    ALTER COLUMN cmc SET NOT NULL,
+    ALTER COLUMN type_line SET DEFAULT '',
+    ALTER COLUMN type_line SET NOT NULL,
+    ALTER COLUMN oracle_text SET DEFAULT '',
+    ALTER COLUMN oracle_text SET NOT NULL;